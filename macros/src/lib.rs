@@ -0,0 +1,233 @@
+//! Procedural macros for [err-ctx](https://docs.rs/err-ctx). Use them through err-ctx's `macros`
+//! feature rather than depending on this crate directly.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, ItemFn, ReturnType};
+
+/// Wrap any error returned by a function in an `err_ctx::Context` formatted from the attribute's
+/// arguments, which are passed to `format!` and may refer to the function's parameters.
+///
+/// ```
+/// use std::{fs, io, path::Path};
+///
+/// #[err_ctx::context("reading config from {}", path.display())]
+/// fn read_config(path: &Path) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+///     Ok(fs::read(path)?)
+/// }
+///
+/// let err = read_config(Path::new("foo.toml")).unwrap_err();
+/// assert!(err.to_string().starts_with("reading config from foo.toml: "));
+/// ```
+///
+/// The context is only formatted if the function fails. Because it is formatted after the body
+/// runs, it can't refer to parameters that the body moves.
+///
+/// The function must return a `Result` whose error type can be converted into a
+/// `Box<dyn Error + Send + Sync>` and constructed `From` an `err_ctx::Context<String>`, such as
+/// `Box<dyn Error + Send + Sync>` itself or `err_ctx::Report`. `async fn`s are supported, but
+/// `impl Trait` return types are not, since the body's result must be annotated with that type:
+///
+/// ```compile_fail
+/// #[err_ctx::context("listing")]
+/// fn list() -> Result<impl Iterator<Item = u32>, Box<dyn std::error::Error + Send + Sync>> {
+///     Ok(0..3)
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn context(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = proc_macro2::TokenStream::from(attr);
+    let mut func = parse_macro_input!(item as ItemFn);
+    if args.is_empty() {
+        return syn::Error::new(Span::call_site(), "expected a format string")
+            .to_compile_error()
+            .into();
+    }
+    let ret = match &func.sig.output {
+        ReturnType::Type(_, ty) => {
+            let ty = quote!(#ty);
+            if let Some(span) = find_impl(ty.clone()) {
+                return syn::Error::new(
+                    span,
+                    "`#[context]` doesn't support `impl Trait` in the return type",
+                )
+                .to_compile_error()
+                .into();
+            }
+            ty
+        }
+        ReturnType::Default => {
+            return syn::Error::new(func.sig.span(), "expected a function returning `Result`")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let body = &func.block;
+    // Hygienic, so that neither the body nor the context's arguments can refer to them
+    let result = syn::Ident::new("result", Span::mixed_site());
+    let x = syn::Ident::new("x", Span::mixed_site());
+    let e = syn::Ident::new("e", Span::mixed_site());
+    // Evaluate the body without moving the parameters, so that the context can refer to them
+    let evaluate = if func.sig.asyncness.is_some() {
+        quote! {
+            let #result: #ret = async { #body }.await;
+        }
+    } else {
+        quote! {
+            #[allow(clippy::redundant_closure_call)]
+            let #result: #ret = (|| #body)();
+        }
+    };
+    // Attribute the `Context` to the attribute's location
+    let wrap = quote_spanned! {args.span()=>
+        match #result {
+            ::core::result::Result::Ok(#x) => ::core::result::Result::Ok(#x),
+            ::core::result::Result::Err(#e) => ::core::result::Result::Err(
+                ::core::convert::From::from(::err_ctx::ErrorExt::ctx(
+                    #e,
+                    ::err_ctx::__private::format!(#args),
+                )),
+            ),
+        }
+    };
+    func.block = syn::parse_quote!({
+        #evaluate
+        #wrap
+    });
+    quote!(#func).into()
+}
+
+/// The span of the first `impl` keyword in `tokens`, if any.
+fn find_impl(tokens: proc_macro2::TokenStream) -> Option<Span> {
+    tokens.into_iter().find_map(|token| match token {
+        proc_macro2::TokenTree::Ident(x) if x == "impl" => Some(x.span()),
+        proc_macro2::TokenTree::Group(x) => find_impl(x.stream()),
+        _ => None,
+    })
+}
+
+/// Implement `Display` for a structured context type from a `#[ctx(...)]` attribute, whose
+/// arguments are passed to `write!`.
+///
+/// Named fields may be referred to by name. Tuple fields are passed as positional arguments, so
+/// each must be referred to, e.g. `{0}`. Enums take an attribute on each variant.
+///
+/// ```
+/// use std::{fs, path::PathBuf};
+/// use err_ctx::{Context, ErrorContext, ResultExt};
+///
+/// #[derive(Debug, ErrorContext)]
+/// #[ctx("reading {path:?}")]
+/// struct ReadingFile {
+///     path: PathBuf,
+/// }
+///
+/// let path = PathBuf::from("foo.txt");
+/// let err = fs::read(&path).ctx(ReadingFile { path }).unwrap_err();
+/// assert!(err.to_string().starts_with("reading \"foo.txt\": "));
+/// // The context remains available in its original form
+/// assert_eq!(err.context().path, PathBuf::from("foo.txt"));
+/// ```
+#[proc_macro_derive(ErrorContext, attributes(ctx))]
+pub fn derive_error_context(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match error_context(&input) {
+        Ok(x) => x.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn error_context(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    // Hygienic, so that fields bound by the same name don't shadow it
+    let f = syn::Ident::new("f", Span::mixed_site());
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let args = ctx_args(&input.attrs, input.ident.span())?;
+            write_fields(quote!(Self), &data.fields, &f, &args)
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let args = ctx_args(&variant.attrs, variant.ident.span())?;
+                    let ident = &variant.ident;
+                    Ok(write_fields(
+                        quote!(Self::#ident),
+                        &variant.fields,
+                        &f,
+                        &args,
+                    ))
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "ErrorContext cannot be derived for unions",
+            ));
+        }
+    };
+    let body = match &input.data {
+        Data::Struct(_) => quote!(match self { #body }),
+        _ => body,
+    };
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::core::fmt::Display for #ident #ty_generics #where_clause {
+            fn fmt(&self, #f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                #[allow(unused_variables)]
+                #body
+            }
+        }
+    })
+}
+
+/// The arguments of the `#[ctx(...)]` attribute among `attrs`.
+fn ctx_args(attrs: &[Attribute], span: Span) -> syn::Result<proc_macro2::TokenStream> {
+    let mut found = None;
+    for attr in attrs {
+        if !attr.path().is_ident("ctx") {
+            continue;
+        }
+        if found.is_some() {
+            return Err(syn::Error::new(attr.span(), "duplicate #[ctx] attribute"));
+        }
+        found = Some(attr.meta.require_list()?.tokens.clone());
+    }
+    found.ok_or_else(|| syn::Error::new(span, "missing #[ctx(\"...\")] attribute"))
+}
+
+/// A match arm binding `fields` of the struct or variant at `path` and writing `args` to `f`.
+fn write_fields(
+    path: proc_macro2::TokenStream,
+    fields: &Fields,
+    f: &syn::Ident,
+    args: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|x| &x.ident);
+            quote!(#path { #(#names),* } => ::core::write!(#f, #args),)
+        }
+        Fields::Unnamed(fields) => {
+            // Bind positionally, so that `{0}` refers to the first field
+            let names = (0..fields.unnamed.len())
+                .map(|i| syn::Ident::new(&format!("__field{}", i), Span::call_site()))
+                .collect::<Vec<_>>();
+            quote!(#path( #(#names),* ) => ::core::write!(#f, #args, #(#names),*),)
+        }
+        Fields::Unit => quote!(#path => ::core::write!(#f, #args),),
+    }
+}
@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::future::Future;
+use std::path::Path;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+const LOAD_LINE: u32 = line!() + 1;
+#[err_ctx::context("loading config from {path:?}")]
+fn load(path: &Path, fail: bool) -> Result<u32> {
+    if fail {
+        return Err("disk on fire".into());
+    }
+    Ok(42)
+}
+
+#[err_ctx::context("loading {} config from {path:?}", kind)]
+async fn load_async(kind: &str, path: &Path, fail: bool) -> Result<u32> {
+    if fail {
+        Err("disk on fire")?;
+    }
+    Ok(42)
+}
+
+#[err_ctx::context("retrying {e} times")]
+fn retry(e: u32) -> Result<()> {
+    Err(format!("gave up after {}", e).into())
+}
+
+struct Noop;
+
+impl Wake for Noop {
+    fn wake(self: Arc<Self>) {}
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Noop));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(x) = future.as_mut().poll(&mut cx) {
+            return x;
+        }
+    }
+}
+
+#[test]
+fn sync() {
+    assert_eq!(load(Path::new("foo"), false).unwrap(), 42);
+    let err = load(Path::new("foo"), true).unwrap_err();
+    assert_eq!(err.to_string(), "loading config from \"foo\": disk on fire");
+}
+
+#[test]
+fn shadowing() {
+    let err = retry(3).unwrap_err();
+    assert_eq!(err.to_string(), "retrying 3 times: gave up after 3");
+}
+
+#[test]
+fn asynchronous() {
+    assert_eq!(
+        block_on(load_async("user", Path::new("foo"), false)).unwrap(),
+        42
+    );
+    let err = block_on(load_async("user", Path::new("foo"), true)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "loading user config from \"foo\": disk on fire"
+    );
+}
+
+#[test]
+fn location() {
+    let err = load(Path::new("foo"), true).unwrap_err();
+    let err = err.downcast::<err_ctx::Context<String>>().unwrap();
+    assert_eq!(err.location().file(), file!());
+    assert_eq!(err.location().line(), LOAD_LINE);
+}
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use err_ctx::{Context, ErrorContext, ErrorExt};
+
+#[derive(Debug, ErrorContext)]
+#[ctx("reading {path:?}")]
+struct Reading {
+    path: PathBuf,
+}
+
+#[derive(Debug, ErrorContext)]
+#[ctx("record {0} of {1}")]
+struct Record(usize, usize);
+
+#[derive(Debug, ErrorContext)]
+#[ctx("starting up")]
+struct Startup;
+
+#[derive(Debug, ErrorContext)]
+enum Phase<T: std::fmt::Display> {
+    #[ctx("parsing {}", input)]
+    Parsing { input: T },
+    #[ctx("stage {0}")]
+    Stage(u32),
+    #[ctx("finishing")]
+    Finishing,
+}
+
+#[derive(Debug, ErrorContext)]
+#[ctx("scaling by {f}")]
+struct Scaling {
+    f: f32,
+}
+
+#[test]
+fn display() {
+    let path = PathBuf::from("foo");
+    assert_eq!(Reading { path }.to_string(), "reading \"foo\"");
+    assert_eq!(Record(2, 3).to_string(), "record 2 of 3");
+    assert_eq!(Startup.to_string(), "starting up");
+    assert_eq!(Phase::Parsing { input: "abc" }.to_string(), "parsing abc");
+    assert_eq!(Phase::<&str>::Stage(7).to_string(), "stage 7");
+    assert_eq!(Phase::<&str>::Finishing.to_string(), "finishing");
+    assert_eq!(Scaling { f: 0.5 }.to_string(), "scaling by 0.5");
+}
+
+#[test]
+fn retrievable() {
+    let err: Box<dyn std::error::Error + Send + Sync> = "disk on fire"
+        .ctx(Reading {
+            path: PathBuf::from("foo"),
+        })
+        .into();
+    let err = err.downcast_ref::<Context<Reading>>().unwrap();
+    assert_eq!(err.context().path, PathBuf::from("foo"));
+}
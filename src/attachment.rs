@@ -0,0 +1,107 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+
+/// Machine-readable key-value pairs attached to a `Context` with
+/// [`Context::attach`](crate::Context::attach).
+///
+/// Attachments don't appear in a `Context`'s single-line `Display` output, but are shown by
+/// [`Report`](crate::Report).
+#[derive(Default)]
+pub struct Attachments {
+    entries: Vec<(&'static str, Box<dyn Value>)>,
+}
+
+impl Attachments {
+    /// The value attached under `key`, if any and if it has type `V`.
+    pub fn get<V: Any>(&self, key: &str) -> Option<&V> {
+        let (_, value) = self.entries.iter().find(|(k, _)| *k == key)?;
+        value.as_any().downcast_ref()
+    }
+
+    /// Iterate over the attachments in the order they were first attached.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &dyn fmt::Display)> {
+        self.entries.iter().map(|(k, v)| (*k, v.as_display()))
+    }
+
+    /// The number of attachments.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no attachments.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Attach `value` under `key`, replacing any existing value.
+    pub(crate) fn insert(&mut self, key: &'static str, value: Box<dyn Value>) {
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, old)) => *old = value,
+            None => self.entries.push((key, value)),
+        }
+    }
+}
+
+impl fmt::Debug for Attachments {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|(k, v)| (k, v)))
+            .finish()
+    }
+}
+
+/// Renders one "key: value" line per attachment.
+impl fmt::Display for Attachments {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}: {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A type-erased attachment value.
+pub(crate) trait Value: fmt::Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_display(&self) -> &dyn fmt::Display;
+}
+
+impl<T: fmt::Display + fmt::Debug + Send + Sync + 'static> Value for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_display(&self) -> &dyn fmt::Display {
+        self
+    }
+}
+
+/// Errors that can carry attachments, i.e. `Context`s.
+///
+/// This trait is sealed, and exists to constrain [`ResultExt::attach`](crate::ResultExt::attach).
+pub trait Attach: Sized + private::Sealed {
+    /// Attach `value` under `key`, replacing any existing value.
+    fn attach<V>(self, key: &'static str, value: V) -> Self
+    where
+        V: fmt::Display + fmt::Debug + Send + Sync + 'static;
+}
+
+impl<C, S> Attach for crate::Context<C, S> {
+    fn attach<V>(self, key: &'static str, value: V) -> Self
+    where
+        V: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        crate::Context::attach(self, key, value)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl<C, S> Sealed for crate::Context<C, S> {}
+}
@@ -0,0 +1,96 @@
+use core::error::Error;
+use core::fmt;
+use core::iter::FusedIterator;
+
+use crate::Context;
+
+/// Iterator over an error and its chain of sources, outermost first.
+///
+/// Each `Context` layer is yielded once, as its own element, followed by its source.
+///
+/// ```
+/// use std::error::Error;
+/// use err_ctx::{Chain, ErrorExt};
+/// let err = "disk on fire".ctx("writing foo.txt").ctx("saving");
+/// let messages = Chain::new(&err).map(|e| e.to_string()).collect::<Vec<_>>();
+/// assert_eq!(messages, [
+///     "saving: writing foo.txt: disk on fire",
+///     "writing foo.txt: disk on fire",
+///     "disk on fire",
+/// ]);
+/// ```
+#[derive(Clone)]
+pub struct Chain<'a> {
+    next: Option<&'a (dyn Error + 'static)>,
+    remaining: usize,
+}
+
+impl<'a> Chain<'a> {
+    pub fn new(head: &'a (dyn Error + 'static)) -> Self {
+        let mut remaining = 1;
+        let mut cursor = head;
+        while let Some(source) = cursor.source() {
+            cursor = source;
+            remaining += 1;
+        }
+        Self {
+            next: Some(head),
+            remaining,
+        }
+    }
+
+    /// Iterate over the contexts of type `D` in the remaining layers, outermost first.
+    ///
+    /// Only layers of type `Context<D>`, i.e. those whose source is boxed, are considered, along
+    /// with those of type `Context<Structured>` whose context holds a `D`. Layers produced by
+    /// `ctx_typed` and similar are never considered, since they can't be identified without
+    /// naming the type of their source.
+    pub fn contexts<D: fmt::Debug + fmt::Display + 'static>(self) -> impl Iterator<Item = &'a D> {
+        self.filter_map(|error| {
+            if let Some(x) = error.downcast_ref::<Context<D>>() {
+                return Some(x.context());
+            }
+            #[cfg(feature = "serde")]
+            if let Some(x) = error.downcast_ref::<Context<crate::Structured>>() {
+                return x.context().downcast_ref();
+            }
+            None
+        })
+    }
+}
+
+impl<'a> Iterator for Chain<'a> {
+    type Item = &'a (dyn Error + 'static);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let error = self.next?;
+        self.next = error.source();
+        self.remaining -= 1;
+        Some(error)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for Chain<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut error = self.next?;
+        for _ in 1..self.remaining {
+            error = error.source()?;
+        }
+        self.remaining -= 1;
+        Some(error)
+    }
+}
+
+impl ExactSizeIterator for Chain<'_> {}
+
+impl FusedIterator for Chain<'_> {}
@@ -0,0 +1,21 @@
+//! ANSI styling for reports.
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+pub(crate) const DIM: &str = "\x1b[2m";
+pub(crate) const BOLD_RED: &str = "\x1b[1;31m";
+pub(crate) const CYAN: &str = "\x1b[36m";
+pub(crate) const RESET: &str = "\x1b[0m";
+
+/// Whether reports printed to standard error should be colored, per the `NO_COLOR` and
+/// `CLICOLOR_FORCE` conventions, falling back to whether standard error is a terminal.
+pub(crate) fn detect() -> bool {
+    if env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty()) {
+        return false;
+    }
+    if env::var_os("CLICOLOR_FORCE").is_some_and(|x| !x.is_empty() && x != "0") {
+        return true;
+    }
+    io::stderr().is_terminal()
+}
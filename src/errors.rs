@@ -0,0 +1,144 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+
+/// A collection of errors sharing a common context, for reporting many failures at once.
+///
+/// ```
+/// use err_ctx::{Errors, ResultExt};
+/// let mut errors = Errors::new("validating config");
+/// for (key, value) in &[("port", "80"), ("timeout", "soon"), ("retries", "-1")] {
+///     if let Err(e) = value.parse::<u32>().ctx(*key) {
+///         errors.push(e);
+///     }
+/// }
+/// let err = errors.into_result(()).unwrap_err();
+/// assert_eq!(err.to_string(), "\
+/// validating config:
+/// ├─ timeout: invalid digit found in string
+/// └─ retries: invalid digit found in string");
+/// ```
+pub struct Errors<C> {
+    context: C,
+    errors: Vec<Box<dyn Error + Send + Sync>>,
+}
+
+impl<C> Errors<C> {
+    /// Construct an empty collection described by `context`.
+    pub fn new(context: C) -> Self {
+        Self {
+            context,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Add an error to the collection.
+    pub fn push(&mut self, error: impl Into<Box<dyn Error + Send + Sync>>) {
+        self.errors.push(error.into());
+    }
+
+    /// The number of errors collected.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Whether no errors have been collected.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The context describing the collection.
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    /// Iterate over the collected errors, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &(dyn Error + Send + Sync + 'static)> {
+        self.errors.iter().map(|x| &**x)
+    }
+
+    /// `Ok(ok)` if no errors have been collected, otherwise `Err(self)`.
+    pub fn into_result<T>(self, ok: T) -> Result<T, Self> {
+        if self.is_empty() {
+            Ok(ok)
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<C, E: Into<Box<dyn Error + Send + Sync>>> Extend<E> for Errors<C> {
+    fn extend<I: IntoIterator<Item = E>>(&mut self, iter: I) {
+        self.errors.extend(iter.into_iter().map(Into::into));
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for Errors<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Errors")
+            .field("context", &self.context)
+            .field("errors", &self.errors)
+            .finish()
+    }
+}
+
+/// Displays the context followed by one line per error, drawn as a tree. Errors whose messages
+/// span multiple lines, such as nested `Errors`, are indented beneath their branch.
+impl<C: fmt::Display> fmt::Display for Errors<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.context)?;
+        for (i, error) in self.errors.iter().enumerate() {
+            let last = i + 1 == self.errors.len();
+            let (branch, indent) = if last {
+                ("└─ ", "   ")
+            } else {
+                ("├─ ", "│  ")
+            };
+            // Render into a buffer so that continuation lines can be indented
+            let message = format!("{}", error);
+            for (j, line) in message.lines().enumerate() {
+                f.write_str("\n")?;
+                f.write_str(if j == 0 { branch } else { indent })?;
+                f.write_str(line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<C: fmt::Debug + fmt::Display> Error for Errors<C> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    use crate::ErrorExt;
+
+    #[test]
+    fn nested() {
+        let mut inner = Errors::new("checking bar");
+        inner.extend(["qux", "quux"].iter().copied());
+        let mut errors = Errors::new("checking foo");
+        errors.push(inner);
+        errors.push("baz".ctx("reading baz"));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors.to_string(),
+            "checking foo:
+├─ checking bar:
+│  ├─ qux
+│  └─ quux
+└─ reading baz: baz"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let errors = Errors::new("checking foo");
+        assert!(errors.is_empty());
+        assert_eq!(errors.into_result(42).unwrap(), 42);
+    }
+}
@@ -0,0 +1,191 @@
+use alloc::boxed::Box;
+use core::error::Error;
+use core::future::Future;
+use core::panic::Location;
+use core::pin::Pin;
+use core::task::{self, Poll};
+
+use pin_project_lite::pin_project;
+
+use crate::{Context, ErrorExt};
+
+/// Extension methods for futures that resolve to a `Result`.
+///
+/// ```
+/// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// use err_ctx::FutureExt;
+/// async fn fetch() -> Result<Vec<u8>, std::io::Error> { Ok(Vec::new()) }
+/// let data = fetch().ctx("fetching foo").await?;
+/// # Ok(()) }
+/// ```
+pub trait FutureExt<T, E>: Future<Output = Result<T, E>> + Sized
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    /// If this future resolves to an `Err`, wrap the error with `context`.
+    #[track_caller]
+    fn ctx<D>(self, context: D) -> CtxFuture<Self, D>;
+
+    /// If this future resolves to an `Err`, invoke `f` and wrap the error with its result.
+    #[track_caller]
+    fn with_ctx<D, F>(self, f: F) -> WithCtxFuture<Self, F>
+    where
+        F: FnOnce(&E) -> D;
+}
+
+impl<T, E, Fut> FutureExt<T, E> for Fut
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    #[track_caller]
+    fn ctx<D>(self, context: D) -> CtxFuture<Self, D> {
+        CtxFuture {
+            future: self,
+            context: Some(context),
+            location: Location::caller(),
+        }
+    }
+
+    #[track_caller]
+    fn with_ctx<D, F>(self, f: F) -> WithCtxFuture<Self, F>
+    where
+        F: FnOnce(&E) -> D,
+    {
+        WithCtxFuture {
+            future: self,
+            f: Some(f),
+            location: Location::caller(),
+        }
+    }
+}
+
+pin_project! {
+    /// Future returned by [`FutureExt::ctx`].
+    #[must_use = "futures do nothing unless polled"]
+    #[derive(Debug)]
+    pub struct CtxFuture<Fut, D> {
+        #[pin]
+        future: Fut,
+        context: Option<D>,
+        location: &'static Location<'static>,
+    }
+}
+
+impl<T, E, Fut, D> Future for CtxFuture<Fut, D>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    type Output = Result<T, Context<D>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let (context, location) = (this.context, *this.location);
+        this.future.poll(cx).map(|result| {
+            result.map_err(|e| {
+                let context = context.take().expect("polled after completion");
+                e.ctx(context).at(location)
+            })
+        })
+    }
+}
+
+pin_project! {
+    /// Future returned by [`FutureExt::with_ctx`].
+    #[must_use = "futures do nothing unless polled"]
+    #[derive(Debug)]
+    pub struct WithCtxFuture<Fut, F> {
+        #[pin]
+        future: Fut,
+        f: Option<F>,
+        location: &'static Location<'static>,
+    }
+}
+
+impl<T, E, Fut, D, F> Future for WithCtxFuture<Fut, F>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+    F: FnOnce(&E) -> D,
+{
+    type Output = Result<T, Context<D>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let (f, location) = (this.f, *this.location);
+        this.future.poll(cx).map(|result| {
+            result.map_err(|e| {
+                let f = f.take().expect("polled after completion");
+                let context = f(&e);
+                e.ctx(context).at(location)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloc::string::{String, ToString};
+    use core::future;
+    use core::ptr;
+    use core::task::{RawWaker, RawWakerVTable, Waker};
+
+    /// A `Waker` that does nothing, for polling on the current thread.
+    pub(crate) fn noop_waker() -> Waker {
+        fn raw() -> RawWaker {
+            RawWaker::new(ptr::null(), &VTABLE)
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(|_| raw(), |_| {}, |_| {}, |_| {});
+        // Safety: the vtable's functions trivially uphold the `RawWaker` contract
+        unsafe { Waker::from_raw(raw()) }
+    }
+
+    /// Poll `future` to completion on the current thread.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = noop_waker();
+        let mut cx = task::Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(x) = future.as_mut().poll(&mut cx) {
+                return x;
+            }
+        }
+    }
+
+    /// Returns `Pending` once before resolving, to exercise repeated polling.
+    fn yield_once<T>(value: T) -> impl Future<Output = T> {
+        let mut value = Some(value);
+        let mut yielded = false;
+        future::poll_fn(move |cx| {
+            if yielded {
+                Poll::Ready(value.take().unwrap())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+    }
+
+    #[test]
+    fn ctx() {
+        let line = line!();
+        let err = block_on(yield_once(Err::<(), _>("bar")).ctx("foo")).unwrap_err();
+        assert_eq!(err.to_string(), "foo: bar");
+        assert_eq!(err.location().line(), line + 1);
+        assert_eq!(
+            block_on(yield_once(Ok::<_, &str>(42)).ctx("foo")).unwrap(),
+            42
+        );
+    }
+
+    #[test]
+    fn with_ctx_is_lazy() {
+        let ok = yield_once(Ok::<_, &str>(())).with_ctx(|_| -> String { unreachable!() });
+        block_on(ok).unwrap();
+        let err = yield_once(Err::<(), _>("bar")).with_ctx(|e| alloc::format!("foo {}", e.len()));
+        assert_eq!(block_on(err).unwrap_err().to_string(), "foo 3: bar");
+    }
+}
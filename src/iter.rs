@@ -0,0 +1,145 @@
+use alloc::boxed::Box;
+use core::error::Error;
+use core::iter::{FromIterator, FusedIterator};
+use core::panic::Location;
+
+use crate::{Context, ErrorExt};
+
+/// Extension methods for iterators of `Result`s.
+///
+/// ```
+/// use err_ctx::IteratorExt;
+/// let input = "1\n2\nthree\n4";
+/// let err = input
+///     .lines()
+///     .map(|line| line.parse::<u32>())
+///     .try_collect_ctx::<Vec<_>, _, _>(|i, _| format!("parsing line {}", i + 1))
+///     .unwrap_err();
+/// assert_eq!(err.to_string(), "parsing line 3: invalid digit found in string");
+/// ```
+pub trait IteratorExt<T, E>: Iterator<Item = Result<T, E>> + Sized
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    /// Wrap each `Err` item with the result of invoking `f` on the item's zero-based index and the
+    /// error.
+    #[track_caller]
+    fn ctx_each<D, F>(self, f: F) -> CtxEach<Self, F>
+    where
+        F: FnMut(usize, &E) -> D;
+
+    /// Collect the `Ok` items into a `B`, or return the first `Err` wrapped with the result of
+    /// invoking `f` on its zero-based index and the error.
+    #[track_caller]
+    fn try_collect_ctx<B, D, F>(self, f: F) -> Result<B, Context<D>>
+    where
+        B: FromIterator<T>,
+        F: FnMut(usize, &E) -> D,
+    {
+        self.ctx_each(f).collect()
+    }
+}
+
+impl<T, E, I> IteratorExt<T, E> for I
+where
+    I: Iterator<Item = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    #[track_caller]
+    fn ctx_each<D, F>(self, f: F) -> CtxEach<Self, F>
+    where
+        F: FnMut(usize, &E) -> D,
+    {
+        CtxEach {
+            iter: self,
+            f,
+            index: 0,
+            location: Location::caller(),
+        }
+    }
+}
+
+/// Iterator returned by [`IteratorExt::ctx_each`].
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Debug, Clone)]
+pub struct CtxEach<I, F> {
+    iter: I,
+    f: F,
+    index: usize,
+    location: &'static Location<'static>,
+}
+
+impl<T, E, I, D, F> Iterator for CtxEach<I, F>
+where
+    I: Iterator<Item = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+    F: FnMut(usize, &E) -> D,
+{
+    type Item = Result<T, Context<D>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let index = self.index;
+        self.index += 1;
+        Some(item.map_err(|e| {
+            let context = (self.f)(index, &e);
+            e.ctx(context).at(self.location)
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, E, I, D, F> ExactSizeIterator for CtxEach<I, F>
+where
+    I: ExactSizeIterator<Item = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+    F: FnMut(usize, &E) -> D,
+{
+}
+
+impl<T, E, I, D, F> FusedIterator for CtxEach<I, F>
+where
+    I: FusedIterator<Item = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+    F: FnMut(usize, &E) -> D,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use alloc::{format, vec};
+
+    #[test]
+    fn ctx_each() {
+        let line = line!();
+        let results = vec![Ok(1), Err("bar"), Ok(2), Err("baz")]
+            .into_iter()
+            .ctx_each(|i, e| format!("item {} ({})", i, e.len()))
+            .map(|x| x.map_err(|e| (e.to_string(), e.location().line())))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            [
+                Ok(1),
+                Err(("item 1 (3): bar".into(), line + 3)),
+                Ok(2),
+                Err(("item 3 (3): baz".into(), line + 3))
+            ]
+        );
+    }
+
+    #[test]
+    fn try_collect_ctx() {
+        let ok = vec![Ok::<_, &str>(1), Ok(2)]
+            .into_iter()
+            .try_collect_ctx::<Vec<_>, _, _>(|i, _| i)
+            .unwrap();
+        assert_eq!(ok, [1, 2]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Inspection of the metadata of layers whose concrete type has been erased, e.g. by boxing.
+//!
+//! A `Context`'s metadata lives in a [`Meta`], which doesn't depend on its type parameters, but
+//! reaching it from a `dyn Error` still requires a downcast to the concrete `Context` type. Each
+//! `Context` type therefore [`register`]s a function performing that downcast when its source is
+//! first requested, which [`inspect`] does before trying every registered function.
+//!
+//! The registry is a lock-free list, so is unavailable on targets without atomic pointer
+//! compare-and-swap, where nested layers' metadata can't be found.
+
+#[cfg(target_has_atomic = "ptr")]
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+#[cfg(feature = "serde")]
+use core::iter;
+use core::panic::Location;
+#[cfg(target_has_atomic = "ptr")]
+use core::{
+    any::TypeId,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+#[cfg(feature = "std")]
+use std::backtrace::{Backtrace, BacktraceStatus};
+
+#[cfg(feature = "serde")]
+use crate::RemoteError;
+use crate::{Attachments, Chain, Context};
+
+/// The metadata of a `Context`.
+pub(crate) struct Meta {
+    #[cfg(feature = "std")]
+    pub(crate) backtrace: Option<Backtrace>,
+    pub(crate) location: &'static Location<'static>,
+    pub(crate) attachments: Attachments,
+}
+
+/// A layer whose metadata is accessible.
+#[derive(Copy, Clone)]
+pub(crate) enum Layer<'a> {
+    Context(&'a Meta),
+    #[cfg(feature = "serde")]
+    Remote(&'a RemoteError),
+}
+
+impl<'a> Layer<'a> {
+    /// Whether this layer is, or was originally, a `Context`.
+    pub(crate) fn is_context(self) -> bool {
+        match self {
+            Layer::Context(_) => true,
+            #[cfg(feature = "serde")]
+            Layer::Remote(x) => x.is_context(),
+        }
+    }
+
+    /// The source location at which this layer was constructed.
+    pub(crate) fn location(self) -> Option<&'a dyn fmt::Display> {
+        match self {
+            Layer::Context(x) => Some(x.location),
+            #[cfg(feature = "serde")]
+            Layer::Remote(x) => x.location.as_ref().map(|x| x as &dyn fmt::Display),
+        }
+    }
+
+    /// The backtrace captured when this layer was constructed, if any.
+    #[cfg(feature = "std")]
+    pub(crate) fn backtrace(self) -> Option<&'a dyn fmt::Display> {
+        match self {
+            Layer::Context(x) => match &x.backtrace {
+                Some(x) if x.status() == BacktraceStatus::Captured => Some(x),
+                _ => None,
+            },
+            #[cfg(feature = "serde")]
+            Layer::Remote(x) => x.backtrace.as_ref().map(|x| x as &dyn fmt::Display),
+        }
+    }
+
+    /// The values attached to this layer, in the order they were attached.
+    pub(crate) fn attachments(self) -> Vec<(&'a str, &'a dyn fmt::Display)> {
+        match self {
+            Layer::Context(x) => x.attachments.iter().collect(),
+            #[cfg(feature = "serde")]
+            Layer::Remote(x) => x
+                .attachments
+                .iter()
+                .map(|(k, v)| (&k[..], v as &dyn fmt::Display))
+                .collect(),
+        }
+    }
+}
+
+/// The metadata of `error`, if it is a `Context` or a `RemoteError`.
+pub(crate) fn inspect<'a>(error: &'a (dyn Error + 'static)) -> Option<Layer<'a>> {
+    // Ensure that `error`'s type is registered if it's a `Context`
+    let _ = error.source();
+    #[cfg(target_has_atomic = "ptr")]
+    if let Some(x) = entries(REGISTRY.load(Ordering::Acquire)).find_map(|x| (x.find)(error)) {
+        return Some(Layer::Context(x));
+    }
+    #[cfg(feature = "serde")]
+    if let Some(x) = error.downcast_ref::<RemoteError>() {
+        return Some(Layer::Remote(x));
+    }
+    None
+}
+
+/// A registered `Context` type.
+#[cfg(target_has_atomic = "ptr")]
+struct Entry {
+    id: TypeId,
+    /// The metadata of an error, if it has this type
+    find: for<'a> fn(&'a (dyn Error + 'static)) -> Option<&'a Meta>,
+    next: *const Entry,
+}
+
+/// The most recently registered `Context` type, which links to those registered before it. Entries
+/// are never freed once published.
+#[cfg(target_has_atomic = "ptr")]
+static REGISTRY: AtomicPtr<Entry> = AtomicPtr::new(ptr::null_mut());
+
+/// Make `Context<C, S>` visible to [`inspect`], if it isn't already.
+pub(crate) fn register<C, S>()
+where
+    C: fmt::Debug + fmt::Display + 'static,
+    S: fmt::Debug + fmt::Display + 'static,
+{
+    #[cfg(target_has_atomic = "ptr")]
+    {
+        fn find<'a, C, S>(error: &'a (dyn Error + 'static)) -> Option<&'a Meta>
+        where
+            C: fmt::Debug + fmt::Display + 'static,
+            S: fmt::Debug + fmt::Display + 'static,
+        {
+            Some(&error.downcast_ref::<Context<C, S>>()?.meta)
+        }
+
+        let id = TypeId::of::<Context<C, S>>();
+        let mut head = REGISTRY.load(Ordering::Acquire);
+        let mut entry = None;
+        loop {
+            if entries(head).any(|x| x.id == id) {
+                return;
+            }
+            let mut new = entry.take().unwrap_or_else(|| {
+                Box::new(Entry {
+                    id,
+                    find: find::<C, S>,
+                    next: ptr::null(),
+                })
+            });
+            new.next = head;
+            let new = Box::into_raw(new);
+            match REGISTRY.compare_exchange(head, new, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return,
+                Err(x) => {
+                    head = x;
+                    // SAFETY: `new` was not published, so is still exclusively ours
+                    entry = Some(unsafe { Box::from_raw(new) });
+                }
+            }
+        }
+    }
+}
+
+/// The entries linked from `head`.
+#[cfg(target_has_atomic = "ptr")]
+fn entries(head: *const Entry) -> impl Iterator<Item = &'static Entry> {
+    // SAFETY: published entries are never freed or mutated
+    let first = unsafe { head.as_ref() };
+    core::iter::successors(first, |x| unsafe { x.next.as_ref() })
+}
+
+/// Whether `error` is a `Context`.
+#[cfg(feature = "std")]
+pub(crate) fn is_context(error: &(dyn Error + 'static)) -> bool {
+    inspect(error).is_some_and(Layer::is_context)
+}
+
+/// Every layer of `error`'s chain, outermost first, with its metadata if accessible. The metadata
+/// of `error` itself is `top`, as it may be known statically where [`inspect`] can't find it.
+#[cfg(feature = "serde")]
+pub(crate) fn layers<'a>(
+    error: &'a (dyn Error + 'static),
+    top: Option<Layer<'a>>,
+) -> impl Iterator<Item = (&'a (dyn Error + 'static), Option<Layer<'a>>)> {
+    iter::once((error, top)).chain(sources(error))
+}
+
+/// Every source in `error`'s chain, outermost first, with its metadata if accessible.
+pub(crate) fn sources<'a>(
+    error: &'a dyn Error,
+) -> impl Iterator<Item = (&'a (dyn Error + 'static), Option<Layer<'a>>)> {
+    let sources = error.source().into_iter().flat_map(Chain::new);
+    sources.map(|x| (x, inspect(x)))
+}
+
+/// Presents a borrowed `Context` as an `Error`, regardless of whether its context is `Debug`.
+pub(crate) struct Borrowed<'a, C, S>(pub &'a Context<C, S>);
+
+impl<C: fmt::Display, S: fmt::Display> fmt::Display for Borrowed<'_, C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<C, S> fmt::Debug for Borrowed<'_, C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Context")
+    }
+}
+
+impl<C: fmt::Display, S: fmt::Display> Error for Borrowed<'_, C, S> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        (self.0.as_error)(&self.0.source)
+    }
+}
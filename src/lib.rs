@@ -10,91 +10,969 @@
 //! let result = fs::read("foo.txt").ctx("reading foo.txt");
 //! assert!(result.unwrap_err().to_string().starts_with("reading foo.txt: "));
 //! ```
+//!
+//! `Option`s can be converted the same way, with `None` becoming a [`Missing`] error:
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use err_ctx::{Missing, OptionExt};
+//! let map = HashMap::<&str, u32>::new();
+//! let err = map.get("foo").ctx("looking up foo").unwrap_err();
+//! assert_eq!(err.to_string(), "looking up foo: missing value");
+//! assert!(std::error::Error::source(&err).unwrap().is::<Missing>());
+//! ```
+//!
+//! # Features
+//!
+//! - `std` (default): backtrace capture and `std`-only conveniences. Without it, the crate is
+//!   `no_std` and requires only `alloc`.
+//! - `color`: ANSI-colored [`Report`]s on terminals. Implies `std`.
+//! - `terminal_size`: wrap [`Report`]s printed to a terminal to its width. Implies `std`.
+//! - `futures`: `TryStreamCtxExt`, for streams of `Result`s.
+//! - `macros`: the `context` attribute, for wrapping every error a function returns, and the
+//!   `ErrorContext` derive, for structured context types.
+//! - `serde`: `Serialize` implementations for [`Context`], [`Chain`], and [`Report`], for
+//!   structured logging, `ctx_serde`, for contexts serialized as structured values, and
+//!   `RemoteError`, which deserializes a chain for display elsewhere. Contexts not constructed
+//!   with `ctx_serde` are serialized as strings, even if they implement `Serialize`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::any::Any;
+use core::error::Error;
+use core::fmt;
+use core::panic::Location;
+#[cfg(feature = "std")]
+use std::backtrace::Backtrace;
+
+use layer::Meta;
+
+#[macro_use]
+mod macros;
+
+mod attachment;
+mod chain;
+#[cfg(feature = "color")]
+mod color;
+mod errors;
+mod future;
+mod iter;
+mod layer;
+mod message;
+#[cfg(feature = "serde")]
+mod remote;
+mod report;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "futures")]
+mod stream;
+#[cfg(feature = "serde")]
+mod structured;
+#[cfg(feature = "std")]
+mod terminal;
 
-use std::error::Error;
-use std::fmt;
+pub use attachment::{Attach, Attachments};
+pub use chain::Chain;
+#[cfg(feature = "macros")]
+pub use err_ctx_macros::{context, ErrorContext};
+pub use errors::Errors;
+pub use future::{CtxFuture, FutureExt, WithCtxFuture};
+pub use iter::{CtxEach, IteratorExt};
+pub use message::Message;
+#[cfg(feature = "serde")]
+pub use remote::RemoteError;
+pub use report::Report;
+#[cfg(feature = "futures")]
+pub use stream::{CtxStream, TryStreamCtxExt, WithCtxStream};
+#[cfg(feature = "serde")]
+pub use structured::Structured;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::format;
+    pub use alloc::string::String;
+}
 
 /// An error providing context for some underlying cause.
-#[derive(Debug)]
-pub struct Context<C> {
+///
+/// The cause is boxed by default. Wrapping it with [`Context::with_source`] or the `ctx_typed`
+/// methods of [`ResultExt`] and [`ErrorExt`] instead preserves its concrete type as `S`, avoiding
+/// an allocation:
+///
+/// ```
+/// use std::{fs, io};
+/// use err_ctx::{Context, ResultExt};
+/// let result: Result<_, Context<&str, io::Error>> =
+///     fs::read("foo.txt").ctx_typed("reading foo.txt");
+/// ```
+pub struct Context<C, S = Box<dyn Error + Send + Sync>> {
     context: C,
-    source: Box<dyn Error + Send + Sync>,
+    source: S,
+    /// The source as an `Error`, or `None` if this `Context` has no source
+    as_error: fn(&S) -> Option<&(dyn Error + 'static)>,
+    /// Boxed to keep `Result`s carrying a `Context` small
+    meta: Box<Meta>,
 }
 
 impl<C> Context<C> {
+    #[track_caller]
     pub fn new(context: C, source: Box<dyn Error + Send + Sync>) -> Self {
-        Self { context, source }
+        Self::with_error(context, source, |x| Some(&**x))
+    }
+
+    /// Construct a `Context` that starts a chain, rather than describing an existing error.
+    ///
+    /// Such a `Context` displays as its context alone, and its [`source_box`](Self::source_box)
+    /// is a placeholder that displays as an empty string.
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use err_ctx::Context;
+    /// let err = Context::without_source("port 80 is reserved");
+    /// assert_eq!(err.to_string(), "port 80 is reserved");
+    /// assert!(err.source().is_none());
+    /// ```
+    #[track_caller]
+    pub fn without_source(context: C) -> Self {
+        Self::with_error(context, Box::new(Root), |_| None)
+    }
+
+    /// The boxed error this `Context` describes.
+    pub fn source_box(&self) -> &(dyn Error + Send + Sync + 'static) {
+        &*self.source
+    }
+}
+
+impl<C, S: Error + 'static> Context<C, S> {
+    /// Construct a `Context` describing `source` without boxing it.
+    #[track_caller]
+    pub fn with_source(context: C, source: S) -> Self {
+        Self::with_error(context, source, |x| Some(x))
+    }
+}
+
+impl<C, S> Context<C, S> {
+    #[track_caller]
+    fn with_error(
+        context: C,
+        source: S,
+        as_error: fn(&S) -> Option<&(dyn Error + 'static)>,
+    ) -> Self {
+        #[cfg(feature = "std")]
+        let backtrace = if as_error(&source).is_some_and(layer::is_context) {
+            None
+        } else {
+            Some(Backtrace::capture())
+        };
+        Self {
+            context,
+            source,
+            as_error,
+            meta: Box::new(Meta {
+                #[cfg(feature = "std")]
+                backtrace,
+                location: Location::caller(),
+                attachments: Attachments::default(),
+            }),
+        }
+    }
+
+    /// Attribute this `Context` to `location`, for use where `#[track_caller]` can't reach.
+    pub(crate) fn at(mut self, location: &'static Location<'static>) -> Self {
+        self.meta.location = location;
+        self
+    }
+
+    /// Attach a machine-readable `value` under `key`, replacing any existing value.
+    ///
+    /// Attachments are omitted from the single-line `Display` output, but shown by [`Report`].
+    ///
+    /// ```
+    /// use err_ctx::ErrorExt;
+    /// let err = "timed out".ctx("fetching foo").attach("retries", 3u32);
+    /// assert_eq!(err.to_string(), "fetching foo: timed out");
+    /// assert_eq!(err.attachment::<u32>("retries"), Some(&3));
+    /// assert_eq!(format!("{:#}", err), "\
+    /// Error: fetching foo
+    ///        retries: 3
+    ///
+    /// Caused by:
+    ///     0: timed out");
+    /// ```
+    pub fn attach<V>(mut self, key: &'static str, value: V) -> Self
+    where
+        V: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.meta.attachments.insert(key, Box::new(value));
+        self
+    }
+
+    /// The value attached to this `Context` under `key`, if any and if it has type `V`.
+    pub fn attachment<V: Any>(&self, key: &str) -> Option<&V> {
+        self.meta.attachments.get(key)
+    }
+
+    /// All values attached to this `Context`.
+    pub fn attachments(&self) -> &Attachments {
+        &self.meta.attachments
+    }
+
+    /// The source location at which this `Context` was constructed.
+    ///
+    /// For errors wrapped by this crate's extension traits, this is the location of the call to
+    /// `ctx` or `with_ctx`.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.meta.location
+    }
+
+    /// The context describing this error.
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    /// Mutable access to the context describing this error.
+    pub fn context_mut(&mut self) -> &mut C {
+        &mut self.context
+    }
+
+    /// Decompose into the context and the error it describes.
+    pub fn into_parts(self) -> (C, S) {
+        (self.context, self.source)
+    }
+
+    /// Replace the context with the result of applying `f` to it, preserving the source,
+    /// backtrace, and location.
+    ///
+    /// ```
+    /// use err_ctx::ErrorExt;
+    /// let err = "disk on fire".ctx("writing foo.txt");
+    /// let err = err.map_context(|c| format!("while {}", c));
+    /// assert_eq!(err.to_string(), "while writing foo.txt: disk on fire");
+    /// ```
+    pub fn map_context<D>(self, f: impl FnOnce(C) -> D) -> Context<D, S> {
+        Context {
+            context: f(self.context),
+            source: self.source,
+            as_error: self.as_error,
+            meta: self.meta,
+        }
+    }
+
+    /// The backtrace captured when this `Context` was constructed.
+    ///
+    /// Only the innermost `Context` in a chain captures a backtrace; this is `None` for layers
+    /// whose source is itself a `Context`. Capture is controlled by the `RUST_BACKTRACE` and
+    /// `RUST_LIB_BACKTRACE` environment variables, as described in [`Backtrace::capture`].
+    #[cfg(feature = "std")]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.meta.backtrace.as_ref()
+    }
+
+    fn source_error(&self) -> Option<&(dyn Error + 'static)> {
+        (self.as_error)(&self.source)
+    }
+}
+
+impl<C, S> Context<C, S>
+where
+    C: fmt::Debug + fmt::Display + 'static,
+    S: fmt::Debug + fmt::Display + 'static,
+{
+    /// Iterate over this error and its chain of sources.
+    pub fn chain(&self) -> Chain<'_> {
+        Chain::new(self)
+    }
+
+    /// The innermost source of this error, or this error itself if it has no source.
+    pub fn root_cause(&self) -> &(dyn Error + 'static) {
+        self.chain().next_back().unwrap()
+    }
+
+    /// Whether this error's chain contains an error of type `E`, i.e. whether
+    /// [`downcast_ref`](Self::downcast_ref) would find one.
+    pub fn is<E: Error + 'static>(&self) -> bool {
+        self.downcast_ref::<E>().is_some()
+    }
+
+    /// Find the outermost error of type `E` in this error's chain, checking each layer's context
+    /// before its source.
+    ///
+    /// ```
+    /// use std::io;
+    /// use err_ctx::ErrorExt;
+    /// let err = io::Error::from(io::ErrorKind::NotFound).ctx("reading foo.txt").ctx("loading");
+    /// assert_eq!(err.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+    /// ```
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        if let Some(x) = (&self.context as &dyn Any).downcast_ref::<E>() {
+            return Some(x);
+        }
+        let mut sources = self.source_error().into_iter().flat_map(Chain::new);
+        sources.find_map(|error| {
+            error
+                .downcast_ref::<E>()
+                .or_else(|| Some(&error.downcast_ref::<Context<E>>()?.context))
+        })
+    }
+
+    /// Find the outermost context of type `D` in this error's chain, searching the layers described
+    /// by [`contexts`](Self::contexts).
+    ///
+    /// ```
+    /// use err_ctx::ErrorExt;
+    /// #[derive(Debug)]
+    /// struct Attempt(u32);
+    /// impl std::fmt::Display for Attempt {
+    ///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    ///         write!(f, "attempt {}", self.0)
+    ///     }
+    /// }
+    /// let err = "timed out".ctx(Attempt(3)).ctx("connecting");
+    /// assert_eq!(err.find_context::<Attempt>().unwrap().0, 3);
+    /// ```
+    pub fn find_context<D: fmt::Debug + fmt::Display + 'static>(&self) -> Option<&D> {
+        self.contexts().next()
+    }
+
+    /// Iterate over the contexts of type `D` in this error's chain, outermost first.
+    ///
+    /// Beyond this layer, contexts are found in layers of type `Context<D>`, i.e. those whose
+    /// source is boxed, as produced by [`ResultExt::ctx`] and similar. A `Structured` context
+    /// holding a `D` is also found.
+    ///
+    /// Contexts of nested layers produced by [`ResultExt::ctx_typed`] and similar are never found,
+    /// since those layers can't be identified without naming the type of their source.
+    ///
+    /// ```
+    /// use err_ctx::{ErrorExt, Missing};
+    /// let err = Missing.ctx_typed(1).ctx(2);
+    /// assert_eq!(err.contexts::<i32>().collect::<Vec<_>>(), [&2]);
+    /// ```
+    pub fn contexts<D: fmt::Debug + fmt::Display + 'static>(&self) -> impl Iterator<Item = &D> {
+        context_as::<D>(&self.context).into_iter().chain(
+            self.source_error()
+                .into_iter()
+                .flat_map(|x| Chain::new(x).contexts()),
+        )
+    }
+}
+
+impl<C: fmt::Debug + fmt::Display + 'static> Context<C> {
+    /// Find the outermost error of type `E` in this error's chain, checking each layer's context
+    /// before its source.
+    ///
+    /// Unlike [`downcast_ref`](Self::downcast_ref), the search only descends through owned layers,
+    /// since `Error::source` only grants shared access. Owned layers are those reached through
+    /// `Context` layers whose source is boxed and whose context is of type `C`, `&'static str`, or
+    /// `String`, so [`is`](Self::is) may find errors that this doesn't.
+    pub fn downcast_mut<E: Error + 'static>(&mut self) -> Option<&mut E> {
+        if (&self.context as &dyn Any).is::<E>() {
+            return (&mut self.context as &mut dyn Any).downcast_mut();
+        }
+        let source = &mut self.source;
+        if source.is::<E>() {
+            return source.downcast_mut();
+        }
+        if source.is::<Self>() {
+            return source.downcast_mut::<Self>()?.downcast_mut();
+        }
+        if source.is::<Context<&'static str>>() {
+            return source
+                .downcast_mut::<Context<&'static str>>()?
+                .downcast_mut();
+        }
+        source.downcast_mut::<Context<String>>()?.downcast_mut()
+    }
+}
+
+impl<C: fmt::Debug + fmt::Display + Send + Sync + 'static> Context<C> {
+    /// Extract the outermost error of type `E` from this error's chain, checking each layer's
+    /// context before its source, or return `self` unchanged if there is none.
+    ///
+    /// Like [`downcast_mut`](Self::downcast_mut), the search only descends through owned layers, so
+    /// [`is`](Self::is) may find errors that this doesn't.
+    ///
+    /// ```
+    /// use std::io;
+    /// use err_ctx::ErrorExt;
+    /// let err = io::Error::from(io::ErrorKind::NotFound).ctx("reading foo.txt").ctx(1);
+    /// assert!(err.is::<io::Error>());
+    /// assert_eq!(err.downcast::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+    /// ```
+    pub fn downcast<E: Error + 'static>(self) -> Result<E, Self> {
+        let Self {
+            context,
+            source,
+            as_error,
+            meta,
+        } = self;
+        let mut context = Some(context);
+        if let Some(x) = (&mut context as &mut dyn Any).downcast_mut::<Option<E>>() {
+            return Ok(x.take().unwrap());
+        }
+        let source = match source.downcast::<E>() {
+            Ok(x) => return Ok(*x),
+            Err(source) => source,
+        };
+        let source = if source.is::<Self>() {
+            downcast_layer::<C, E>(source)
+        } else if source.is::<Context<&'static str>>() {
+            downcast_layer::<&'static str, E>(source)
+        } else {
+            downcast_layer::<String, E>(source)
+        };
+        match source {
+            Ok(x) => Ok(x),
+            Err(source) => Err(Self {
+                context: context.unwrap(),
+                source,
+                as_error,
+                meta,
+            }),
+        }
+    }
+}
+
+/// `context` as a `D`, looking through a `Structured` context.
+fn context_as<D: Any>(context: &dyn Any) -> Option<&D> {
+    #[cfg(feature = "serde")]
+    if let Some(x) = context.downcast_ref::<Structured>() {
+        if let Some(x) = x.downcast_ref() {
+            return Some(x);
+        }
+    }
+    context.downcast_ref()
+}
+
+/// Extract an error of type `E` from `source` if it's a `Context<D>`, per [`Context::downcast`].
+fn downcast_layer<D, E>(
+    source: Box<dyn Error + Send + Sync>,
+) -> Result<E, Box<dyn Error + Send + Sync>>
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    E: Error + 'static,
+{
+    match source.downcast::<Context<D>>() {
+        Ok(x) => x
+            .downcast()
+            .map_err(|x| Box::new(x) as Box<dyn Error + Send + Sync>),
+        Err(source) => Err(source),
+    }
+}
+
+impl<C: fmt::Debug, S: fmt::Debug> fmt::Debug for Context<C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct("Context");
+        s.field("context", &self.context);
+        s.field("source", &self.source);
+        #[cfg(feature = "std")]
+        s.field("backtrace", &self.meta.backtrace);
+        s.field("location", &self.meta.location);
+        s.field("attachments", &self.meta.attachments);
+        s.finish()
     }
 }
 
-impl<C: fmt::Display> fmt::Display for Context<C> {
+/// Displays as "context: cause" on a single line, or as a multi-line [`Report`] when the alternate
+/// flag is set.
+impl<C: fmt::Display, S: fmt::Display> fmt::Display for Context<C, S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("Error: ")?;
+            let top = Some(layer::Layer::Context(&self.meta));
+            return report::write(f, &layer::Borrowed(self), top, &report::Options::default());
+        }
         self.context.fmt(f)?;
-        f.write_str(": ")?;
-        self.source.fmt(f)
+        if self.source_error().is_some() {
+            f.write_str(": ")?;
+            self.source.fmt(f)?;
+        }
+        Ok(())
     }
 }
 
-impl<C: fmt::Debug + fmt::Display> Error for Context<C> {
+impl<C, S> Error for Context<C, S>
+where
+    C: fmt::Debug + fmt::Display + 'static,
+    S: fmt::Debug + fmt::Display + 'static,
+{
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(&*self.source)
+        // Make this layer's metadata visible where its type has been erased
+        layer::register::<C, S>();
+        self.source_error()
+    }
+}
+
+/// Placeholder source of a `Context` constructed with [`Context::without_source`].
+#[derive(Debug)]
+struct Root;
+
+impl fmt::Display for Root {
+    fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
     }
 }
 
+impl Error for Root {}
+
 pub trait ResultExt<T, E>
 where
     E: Into<Box<dyn Error + Send + Sync>>,
 {
     /// If this `Result` is an `Err`, wrap the error with `context`.
+    #[track_caller]
     fn ctx<D>(self, context: D) -> Result<T, Context<D>>;
 
     /// If this `Result` is an `Err`, invoke `f` and wrap the error with its result.
+    #[track_caller]
     fn with_ctx<D>(self, f: impl FnOnce(&E) -> D) -> Result<T, Context<D>>;
+
+    /// If this `Result` is an `Err` carrying a `Context`, attach `value` to it under `key`.
+    ///
+    /// ```
+    /// use err_ctx::ResultExt;
+    /// let result = Err::<(), _>("timed out").ctx("fetching foo").attach("retries", 3u32);
+    /// assert_eq!(result.unwrap_err().attachment::<u32>("retries"), Some(&3));
+    /// ```
+    fn attach<V>(self, key: &'static str, value: V) -> Self
+    where
+        E: Attach,
+        V: fmt::Display + fmt::Debug + Send + Sync + 'static;
+
+    /// If this `Result` is an `Err`, wrap the error with `context` without boxing it.
+    #[track_caller]
+    fn ctx_typed<D>(self, context: D) -> Result<T, Context<D, E>>
+    where
+        E: Error + 'static;
+
+    /// If this `Result` is an `Err`, wrap the error with `context`, to be serialized as a
+    /// structured value.
+    #[cfg(feature = "serde")]
+    #[track_caller]
+    fn ctx_serde<D>(self, context: D) -> Result<T, Context<Structured>>
+    where
+        D: serde::Serialize + fmt::Display + fmt::Debug + Send + Sync + 'static;
+
+    /// If this `Result` is an `Err`, invoke `f` and wrap the error with its result without boxing
+    /// it.
+    #[track_caller]
+    fn with_ctx_typed<D>(self, f: impl FnOnce(&E) -> D) -> Result<T, Context<D, E>>
+    where
+        E: Error + 'static;
 }
 
 impl<T, E> ResultExt<T, E> for Result<T, E>
 where
     E: Into<Box<dyn Error + Send + Sync>>,
 {
+    // Closures can't propagate the caller's location, hence `match` rather than `map_err`.
+
+    #[track_caller]
     fn ctx<D>(self, context: D) -> Result<T, Context<D>> {
-        self.map_err(|e| e.ctx(context))
+        match self {
+            Ok(x) => Ok(x),
+            Err(e) => Err(e.ctx(context)),
+        }
     }
 
+    #[track_caller]
     fn with_ctx<D>(self, f: impl FnOnce(&E) -> D) -> Result<T, Context<D>> {
-        self.map_err(|e| {
-            let context = f(&e);
-            e.ctx(context)
-        })
+        match self {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                let context = f(&e);
+                Err(e.ctx(context))
+            }
+        }
+    }
+
+    fn attach<V>(self, key: &'static str, value: V) -> Self
+    where
+        E: Attach,
+        V: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.map_err(|e| e.attach(key, value))
+    }
+
+    #[track_caller]
+    fn ctx_typed<D>(self, context: D) -> Result<T, Context<D, E>>
+    where
+        E: Error + 'static,
+    {
+        match self {
+            Ok(x) => Ok(x),
+            Err(e) => Err(Context::with_source(context, e)),
+        }
+    }
+
+    #[track_caller]
+    fn with_ctx_typed<D>(self, f: impl FnOnce(&E) -> D) -> Result<T, Context<D, E>>
+    where
+        E: Error + 'static,
+    {
+        match self {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                let context = f(&e);
+                Err(Context::with_source(context, e))
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[track_caller]
+    fn ctx_serde<D>(self, context: D) -> Result<T, Context<Structured>>
+    where
+        D: serde::Serialize + fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        match self {
+            Ok(x) => Ok(x),
+            Err(e) => Err(e.ctx_serde(context)),
+        }
     }
 }
 
 pub trait ErrorExt {
     /// Construct a `Context` wrapping this error.
+    #[track_caller]
     fn ctx<D>(self, context: D) -> Context<D>;
+
+    /// Construct a `Context` wrapping this error without boxing it.
+    #[track_caller]
+    fn ctx_typed<D>(self, context: D) -> Context<D, Self>
+    where
+        Self: Error + Sized + 'static;
+
+    /// Construct a `Context` wrapping this error, whose context is serialized as a structured
+    /// value.
+    #[cfg(feature = "serde")]
+    #[track_caller]
+    fn ctx_serde<D>(self, context: D) -> Context<Structured>
+    where
+        D: serde::Serialize + fmt::Display + fmt::Debug + Send + Sync + 'static;
 }
 
-impl<T: Into<Box<Error + Send + Sync>>> ErrorExt for T {
+impl<T: Into<Box<dyn Error + Send + Sync>>> ErrorExt for T {
+    #[track_caller]
     fn ctx<D>(self, context: D) -> Context<D> {
-        Context {
-            context,
-            source: self.into(),
+        Context::new(context, self.into())
+    }
+
+    #[track_caller]
+    fn ctx_typed<D>(self, context: D) -> Context<D, Self>
+    where
+        Self: Error + Sized + 'static,
+    {
+        Context::with_source(context, self)
+    }
+
+    #[cfg(feature = "serde")]
+    #[track_caller]
+    fn ctx_serde<D>(self, context: D) -> Context<Structured>
+    where
+        D: serde::Serialize + fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        Context::new(Structured::new(context), self.into())
+    }
+}
+
+pub trait OptionExt<T> {
+    /// If this `Option` is `None`, produce a [`Missing`] error wrapped with `context`.
+    #[track_caller]
+    fn ctx<D>(self, context: D) -> Result<T, Context<D>>;
+
+    /// If this `Option` is `None`, invoke `f` and wrap a [`Missing`] error with its result.
+    #[track_caller]
+    fn with_ctx<D>(self, f: impl FnOnce() -> D) -> Result<T, Context<D>>;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    #[track_caller]
+    fn ctx<D>(self, context: D) -> Result<T, Context<D>> {
+        match self {
+            Some(x) => Ok(x),
+            None => Err(Missing.ctx(context)),
         }
     }
+
+    #[track_caller]
+    fn with_ctx<D>(self, f: impl FnOnce() -> D) -> Result<T, Context<D>> {
+        match self {
+            Some(x) => Ok(x),
+            None => Err(Missing.ctx(f())),
+        }
+    }
+}
+
+/// The underlying cause of a `Context` constructed from a `None` by [`OptionExt`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct Missing;
+
+impl fmt::Display for Missing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("missing value")
+    }
 }
 
+impl Error for Missing {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
 
     #[allow(dead_code)]
     fn wrap_box() -> Result<(), impl Error + Send + Sync> {
-        let x: Result<(), Box<dyn std::error::Error + Send + Sync>> = Err("foo".into());
+        let x: Result<(), Box<dyn Error + Send + Sync>> = Err("foo".into());
         x.ctx("bar")
     }
 
+    #[cfg(feature = "std")]
     #[allow(dead_code)]
     fn to_box() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         std::fs::read("foo.txt").ctx("reading foo.txt")?;
         Ok(())
     }
+
+    #[test]
+    fn option_missing() {
+        let err = None::<()>.ctx("finding foo").unwrap_err();
+        assert_eq!(err.to_string(), "finding foo: missing value");
+        assert!(err.source().unwrap().is::<Missing>());
+        assert_eq!(
+            Some(42).with_ctx(|| -> &str { unreachable!() }).unwrap(),
+            42
+        );
+    }
+
+    #[test]
+    fn chain() {
+        let err = "baz".ctx("bar").ctx("foo");
+        let mut chain = err.chain();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.next_back().unwrap().to_string(), "baz");
+        assert_eq!(chain.next().unwrap().to_string(), "foo: bar: baz");
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain.next().unwrap().to_string(), "bar: baz");
+        assert!(chain.next().is_none());
+        assert!(chain.next_back().is_none());
+        assert_eq!(err.root_cause().to_string(), "baz");
+    }
+
+    #[test]
+    fn report() {
+        let err = "baz".ctx("bar").ctx("foo");
+        assert_eq!(
+            format!("{:#}", err),
+            "Error: foo\n\nCaused by:\n    0: bar\n    1: baz"
+        );
+        assert_eq!(Report::new("foo").to_string(), "Error: foo");
+        let err = "line one\nline two".ctx("foo");
+        assert_eq!(
+            format!("{:#}", err),
+            "Error: foo\n\nCaused by:\n    0: line one\n       line two"
+        );
+    }
+
+    #[test]
+    fn report_repeated_cause() {
+        #[derive(Debug)]
+        struct Verbose(Box<dyn Error + Send + Sync>);
+        impl fmt::Display for Verbose {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "verbose: {}", self.0)
+            }
+        }
+        impl Error for Verbose {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&*self.0)
+            }
+        }
+        let err = Verbose("baz".into()).ctx("foo");
+        assert_eq!(
+            format!("{:#}", err),
+            "Error: foo\n\nCaused by:\n    0: verbose\n    1: baz"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn backtrace_captured_once() {
+        let err = "bar".ctx("foo");
+        assert!(err.backtrace().is_some());
+        let err = err.ctx("baz");
+        assert!(err.backtrace().is_none());
+
+        // Whatever the types of the wrapped `Context`
+        let err = "bar".ctx(1).ctx("foo");
+        assert!(err.backtrace().is_none());
+        let err = Missing.ctx_typed("bar").ctx("foo");
+        assert!(err.backtrace().is_none());
+        let err = Missing.ctx_typed("bar").ctx_typed(2);
+        assert!(err.backtrace().is_none());
+    }
+
+    #[test]
+    fn location() {
+        let line = line!();
+        let err = Err::<(), _>("foo").ctx("bar").unwrap_err();
+        assert_eq!(err.location().file(), file!());
+        assert_eq!(err.location().line(), line + 1);
+        let err = None::<()>.with_ctx(|| "bar").unwrap_err();
+        assert_eq!(err.location().line(), line + 4);
+        let report = Report::new(err.ctx("baz")).show_locations(true).to_string();
+        let expected = format!(
+            "Error: baz\n       at {file}:{outer}:38\n\nCaused by:\n    0: bar\n       at {file}:{inner}:30\n    1: missing value",
+            file = file!(),
+            outer = line + 6,
+            inner = line + 4,
+        );
+        assert_eq!(report, expected);
+
+        // Nested layers' locations are shown whatever their context types
+        let line = line!();
+        let err = "bar".ctx(Foo(1)).ctx("baz");
+        let report = Report::new(err).show_locations(true).to_string();
+        let expected = format!(
+            "Error: baz\n       at {file}:{line}:37\n\nCaused by:\n    0: foo 1\n       at {file}:{line}:25\n    1: bar",
+            file = file!(),
+            line = line + 1,
+        );
+        assert_eq!(report, expected);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Foo(u32);
+
+    impl fmt::Display for Foo {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "foo {}", self.0)
+        }
+    }
+
+    impl Error for Foo {}
+
+    #[test]
+    fn downcast() {
+        let mut err = Foo(1).ctx("bar").ctx("baz");
+        assert!(err.is::<Foo>());
+        assert!(!err.is::<Missing>());
+        assert_eq!(err.downcast_ref::<Foo>(), Some(&Foo(1)));
+        err.downcast_mut::<Foo>().unwrap().0 = 2;
+        assert_eq!(err.to_string(), "baz: bar: foo 2");
+        let err = err.downcast::<Missing>().unwrap_err();
+        assert_eq!(err.downcast::<Foo>().unwrap(), Foo(2));
+
+        let err = "bar".ctx(Foo(3)).ctx("baz");
+        assert_eq!(err.downcast_ref::<Foo>(), Some(&Foo(3)));
+        let err = Missing.ctx(Foo(4));
+        assert_eq!(err.downcast::<Foo>().unwrap(), Foo(4));
+
+        // Boxed layers with string contexts are searched whatever the outer context type
+        let mut err = Foo(5).ctx("bar").ctx(String::from("baz")).ctx(Foo(6));
+        assert!(err.is::<Foo>());
+        err.downcast_mut::<Foo>().unwrap().0 = 7;
+        assert_eq!(err.downcast_ref::<Foo>(), Some(&Foo(7)));
+        let err = err.map_context(|_| 0);
+        assert!(err.is::<Foo>());
+        assert_eq!(err.downcast::<Foo>().unwrap(), Foo(5));
+
+        // Other layers are only searched by `is` and `downcast_ref`
+        let err = Foo(8).ctx(Foo(9)).ctx("baz");
+        assert!(err.is::<Foo>());
+        assert_eq!(err.downcast_ref::<Foo>(), Some(&Foo(9)));
+        assert!(err.downcast::<Foo>().is_err());
+        let err = Foo(10).ctx_typed("bar").ctx("baz");
+        assert_eq!(err.is::<Foo>(), err.downcast_ref::<Foo>().is_some());
+    }
+
+    #[test]
+    fn parts() {
+        let mut err = "bar".ctx("foo");
+        assert_eq!(*err.context(), "foo");
+        *err.context_mut() = "baz";
+        assert_eq!(err.source_box().to_string(), "bar");
+        let location = err.location();
+        let err = err.map_context(|c| c.len());
+        assert_eq!(err.location(), location);
+        let (context, source) = err.into_parts();
+        assert_eq!(context, 3);
+        assert_eq!(source.to_string(), "bar");
+    }
+
+    #[test]
+    fn typed_source() {
+        let err: Context<&str, Foo> = Err::<(), _>(Foo(1)).ctx_typed("bar").unwrap_err();
+        assert_eq!(err.to_string(), "bar: foo 1");
+        assert_eq!(err.downcast_ref::<Foo>(), Some(&Foo(1)));
+        let err = err.ctx_typed("baz");
+        assert_eq!(err.root_cause().to_string(), "foo 1");
+        assert_eq!(
+            format!("{:#}", err),
+            "Error: baz\n\nCaused by:\n    0: bar\n    1: foo 1"
+        );
+        let (_, inner) = err.into_parts();
+        assert_eq!(inner.into_parts().1, Foo(1));
+    }
+
+    #[test]
+    fn message_root() {
+        let err = Message::new("bar").ctx("foo");
+        assert_eq!(format!("{:#}", err), "Error: foo\n\nCaused by:\n    0: bar");
+        assert_eq!(err.downcast_ref::<Message>().unwrap().as_str(), "bar");
+        assert_eq!(Message::from(format!("{}", 42)).as_str(), "42");
+    }
+
+    #[test]
+    fn contexts() {
+        let err = "baz".ctx(1).ctx("bar").ctx(2);
+        assert_eq!(err.find_context::<i32>(), Some(&2));
+        assert_eq!(err.contexts::<i32>().copied().collect::<Vec<_>>(), [2, 1]);
+        assert_eq!(err.contexts::<&str>().copied().collect::<Vec<_>>(), ["bar"]);
+        assert_eq!(err.find_context::<u8>(), None);
+        assert_eq!(err.to_string(), "2: bar: 1: baz");
+    }
+
+    #[test]
+    fn attachments() {
+        let err = "baz"
+            .ctx("bar")
+            .attach("path", "/tmp/bar")
+            .attach("tries", 1)
+            .attach("tries", 2);
+        assert_eq!(err.attachment::<i32>("tries"), Some(&2));
+        assert_eq!(err.attachment::<u8>("tries"), None);
+        assert_eq!(err.attachments().len(), 2);
+        let err = Err::<(), _>(err).ctx("foo").attach("id", 7).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "Error: foo\n       id: 7\n\nCaused by:\n    0: bar\n       path: /tmp/bar\n       tries: 2\n    1: baz"
+        );
+        let report = Report::new(err).show_attachments(false).to_string();
+        assert_eq!(report, "Error: foo\n\nCaused by:\n    0: bar\n    1: baz");
+
+        let err = "baz".ctx("bar").ctx("foo").attach("raw", "a\0b");
+        assert_eq!(
+            format!("{:#}", err),
+            "Error: foo\n       raw: a\0b\n\nCaused by:\n    0: bar\n    1: baz"
+        );
+
+        // Whatever the context types of the nested layers
+        let err = "disk".ctx(Foo(1)).attach("k", 1).ctx("saving");
+        assert_eq!(
+            format!("{:#}", err),
+            "Error: saving\n\nCaused by:\n    0: foo 1\n       k: 1\n    1: disk"
+        );
+        let err = Foo(2).ctx_typed(Foo(1)).attach("k", 1).ctx("saving");
+        assert_eq!(
+            format!("{:#}", err),
+            "Error: saving\n\nCaused by:\n    0: foo 1\n       k: 1\n    1: foo 2"
+        );
+    }
 }
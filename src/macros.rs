@@ -0,0 +1,123 @@
+/// Wrap the error of a `Result` with a formatted context, formatting only if it is an `Err`.
+///
+/// Equivalent to `result.with_ctx(|_| format!(...))`.
+///
+/// ```
+/// use std::{fs, path::Path};
+/// use err_ctx::ctx;
+/// let path = Path::new("foo.txt");
+/// let err = ctx!(fs::read(path), "reading {}", path.display()).unwrap_err();
+/// assert!(err.to_string().starts_with("reading foo.txt: "));
+/// ```
+#[macro_export]
+macro_rules! ctx {
+    ($result:expr, $($arg:tt)+) => {
+        match $result {
+            ::core::result::Result::Ok(x) => ::core::result::Result::Ok(x),
+            ::core::result::Result::Err(e) => ::core::result::Result::Err($crate::ErrorExt::ctx(
+                e,
+                $crate::__private::format!($($arg)+),
+            )),
+        }
+    };
+}
+
+/// Return early with a `Context<String>` without a source, as constructed by
+/// [`Context::without_source`](crate::Context::without_source), whose context is formatted from
+/// the arguments.
+///
+/// The error is converted into the function's error type with `From`, as by `?`.
+///
+/// ```
+/// use err_ctx::{bail, Context};
+/// fn check(port: u32) -> Result<(), Context<String>> {
+///     if port == 0 {
+///         bail!("invalid port {}", port);
+///     }
+///     Ok(())
+/// }
+/// let err = check(0).unwrap_err();
+/// assert_eq!(err.to_string(), "invalid port 0");
+/// assert_eq!(err.context(), "invalid port 0");
+/// ```
+#[macro_export]
+macro_rules! bail {
+    ($($arg:tt)+) => {
+        return ::core::result::Result::Err(::core::convert::From::from(
+            $crate::Context::<$crate::__private::String>::without_source(
+                $crate::__private::format!($($arg)+),
+            ),
+        ))
+    };
+}
+
+/// Return early with a `Context<String>` without a source, as by [`bail!`], if a condition is
+/// false.
+///
+/// ```
+/// use err_ctx::ensure;
+/// fn check(port: u32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+///     ensure!(port != 0, "invalid port {}", port);
+///     Ok(())
+/// }
+/// assert_eq!(check(0).unwrap_err().to_string(), "invalid port 0");
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::bail!($($arg)+);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use core::error::Error;
+    use core::fmt;
+
+    use crate::Context;
+
+    /// Display that panics, to verify that formatting is skipped on success.
+    struct Unreachable;
+
+    impl fmt::Display for Unreachable {
+        fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn ctx_lazy() {
+        assert_eq!(ctx!(Ok::<_, &str>(42), "{}", Unreachable).unwrap(), 42);
+        let line = line!();
+        let err = ctx!(Err::<(), _>("bar"), "foo {}", 42).unwrap_err();
+        assert_eq!(err.to_string(), "foo 42: bar");
+        assert_eq!(err.location().line(), line + 1);
+    }
+
+    #[test]
+    fn ensure() {
+        fn check(x: u32) -> Result<u32, Box<dyn Error + Send + Sync>> {
+            ensure!(x < 10, "{} is too big", x);
+            Ok(x)
+        }
+        assert_eq!(check(3).unwrap(), 3);
+        let err = check(30).unwrap_err();
+        assert_eq!(err.to_string(), "30 is too big");
+        assert!(err.is::<Context<String>>());
+        assert!(err.source().is_none());
+
+        fn check_typed(x: u32) -> Result<u32, Context<String>> {
+            ensure!(x < 10, "{} is too big", x);
+            Ok(x)
+        }
+        let err = check_typed(30).unwrap_err();
+        assert_eq!(err.context(), "30 is too big");
+        assert_eq!(err.location().file(), file!());
+        assert_eq!(format!("{:#}", err), "Error: 30 is too big");
+    }
+}
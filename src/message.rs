@@ -0,0 +1,56 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use core::error::Error;
+use core::fmt;
+
+/// An error consisting only of a message, for starting a chain where there is no underlying error
+/// to wrap.
+///
+/// ```
+/// use err_ctx::{ErrorExt, Message};
+/// let err = Message::from(format_args!("port {} is reserved", 80)).ctx("validating config");
+/// assert_eq!(err.to_string(), "validating config: port 80 is reserved");
+/// assert!(err.is::<Message>());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Message(Cow<'static, str>);
+
+impl Message {
+    pub fn new(message: impl Into<Cow<'static, str>>) -> Self {
+        Self(message.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(x: &'static str) -> Self {
+        Self(Cow::Borrowed(x))
+    }
+}
+
+impl From<String> for Message {
+    fn from(x: String) -> Self {
+        Self(Cow::Owned(x))
+    }
+}
+
+/// Formats `x`, unless it consists of a single string literal.
+impl From<fmt::Arguments<'_>> for Message {
+    fn from(x: fmt::Arguments<'_>) -> Self {
+        match x.as_str() {
+            Some(s) => Self(Cow::Borrowed(s)),
+            None => Self(Cow::Owned(x.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for Message {}
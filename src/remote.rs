@@ -0,0 +1,217 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+
+use crate::{layer, report};
+
+/// An error chain reconstructed from its serialized form, e.g. after crossing a process boundary.
+///
+/// Each layer of the original chain becomes a `RemoteError` whose `source` is the next, so the
+/// chain displays, walks, and renders as a [`Report`](crate::Report) just like the original,
+/// including the locations and attachments of its `Context` layers. Only the text of each layer
+/// survives; the original types cannot be recovered.
+///
+/// ```
+/// use err_ctx::{ErrorExt, RemoteError};
+/// let err = "disk on fire".ctx("writing foo.txt").ctx("saving");
+/// let json = serde_json::to_string(&err).unwrap();
+/// let remote = serde_json::from_str::<RemoteError>(&json).unwrap();
+/// assert_eq!(remote.to_string(), err.to_string());
+/// assert_eq!(format!("{:#}", remote), format!("{:#}", err));
+/// ```
+#[derive(Debug)]
+pub struct RemoteError {
+    message: String,
+    context: bool,
+    pub(crate) location: Option<String>,
+    pub(crate) attachments: Vec<(String, String)>,
+    pub(crate) backtrace: Option<String>,
+    source: Option<Box<RemoteError>>,
+}
+
+impl RemoteError {
+    /// Whether this layer was originally a `Context`.
+    pub fn is_context(&self) -> bool {
+        self.context
+    }
+
+    /// The source location at which this layer was originally constructed, if it was a `Context`.
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// The attachments of this layer, rendered as strings, in the order they were attached.
+    pub fn attachments(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attachments.iter().map(|(k, v)| (&k[..], &v[..]))
+    }
+
+    /// The backtrace captured when this layer was originally constructed, if any.
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_deref()
+    }
+}
+
+/// Displays as the original layer did, or as a multi-line [`Report`](crate::Report) when the
+/// alternate flag is set.
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("Error: ")?;
+            return report::write(f, self, layer::inspect(self), &report::Options::default());
+        }
+        f.write_str(&self.message)?;
+        match &self.source {
+            Some(source) if self.context => write!(f, ": {}", source),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Error for RemoteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&**self.source.as_ref()?)
+    }
+}
+
+/// Deserializes the form produced by the `Serialize` implementation of [`Context`](crate::Context),
+/// [`Chain`](crate::Chain), [`Report`](crate::Report), or `RemoteError`.
+impl<'de> Deserialize<'de> for RemoteError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = Serialized::deserialize(deserializer)?;
+        // The backtrace belongs to the innermost `Context`, which is the only one to capture it
+        let innermost = serialized.layers.iter().rposition(|x| x.context);
+        let mut backtrace = serialized.backtrace;
+        let mut source = None;
+        for (i, layer) in serialized.layers.into_iter().enumerate().rev() {
+            source = Some(Box::new(RemoteError {
+                message: layer.message,
+                context: layer.context,
+                location: layer.location,
+                attachments: layer.attachments,
+                backtrace: match Some(i) == innermost {
+                    true => backtrace.take(),
+                    false => None,
+                },
+                source,
+            }));
+        }
+        match source {
+            Some(x) => Ok(*x),
+            None => Err(de::Error::invalid_length(0, &"at least one layer")),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Serialized {
+    backtrace: Option<String>,
+    layers: Vec<Layer>,
+}
+
+#[derive(serde::Deserialize)]
+struct Layer {
+    message: String,
+    context: bool,
+    location: Option<String>,
+    #[serde(deserialize_with = "pairs")]
+    attachments: Vec<(String, String)>,
+}
+
+/// Deserialize a map as a list of pairs, preserving its order.
+fn pairs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, String)>, D::Error> {
+    struct Pairs;
+
+    impl<'de> Visitor<'de> for Pairs {
+        type Value = Vec<(String, String)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map of strings")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut pairs = Vec::new();
+            while let Some(pair) = map.next_entry()? {
+                pairs.push(pair);
+            }
+            Ok(pairs)
+        }
+    }
+
+    deserializer.deserialize_map(Pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    use crate::{Chain, Context, ErrorExt, Report};
+
+    #[test]
+    fn chain() {
+        let err = "disk on fire"
+            .ctx("writing foo.txt")
+            .attach("attempt", 3)
+            .ctx("saving");
+        let json = serde_json::to_string(&err).unwrap();
+        let remote = serde_json::from_str::<RemoteError>(&json).unwrap();
+        let messages = |e| Chain::new(e).map(|x| x.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages(&remote), messages(&err));
+        let inner = remote.source.as_deref().unwrap();
+        assert!(remote.is_context() && inner.is_context());
+        assert!(!inner.source.as_deref().unwrap().is_context());
+        assert_eq!(inner.attachments().collect::<Vec<_>>(), [("attempt", "3")]);
+        let location = err
+            .source_box()
+            .downcast_ref::<Context<&str>>()
+            .unwrap()
+            .location();
+        assert_eq!(inner.location(), Some(&location.to_string()[..]));
+        // Formatting flags other than the alternate flag are ignored
+        assert_eq!(alloc::format!("{:-2}", remote), remote.to_string());
+
+        let original = Report::new(err).show_locations(true).to_string();
+        assert_eq!(
+            Report::new(remote).show_locations(true).to_string(),
+            original
+        );
+
+        // Serializing again is lossless
+        let remote = serde_json::from_str::<RemoteError>(&json).unwrap();
+        assert_eq!(serde_json::to_string(&remote).unwrap(), json);
+    }
+
+    #[test]
+    fn empty() {
+        assert!(serde_json::from_str::<RemoteError>(r#"{"layers":[]}"#).is_err());
+    }
+
+    #[cfg(all(unix, feature = "std"))]
+    #[test]
+    fn pipe() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+
+        use crate::ResultExt;
+
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        let worker = std::thread::spawn(move || {
+            let err = std::fs::read("/nonexistent")
+                .ctx("reading config")
+                .unwrap_err();
+            let err = ErrorExt::ctx(err, "starting worker").attach("worker", 7);
+            serde_json::to_writer(&mut tx, &err).unwrap();
+            tx.write_all(b"\n").unwrap();
+            format!("{:#}", err)
+        });
+        let mut line = String::new();
+        BufReader::new(rx).read_line(&mut line).unwrap();
+        let remote = serde_json::from_str::<RemoteError>(&line).unwrap();
+        assert_eq!(format!("{:#}", remote), worker.join().unwrap());
+        assert!(Chain::new(&remote).last().unwrap().source().is_none());
+    }
+}
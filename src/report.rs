@@ -0,0 +1,619 @@
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{self, Write};
+use core::iter;
+
+use crate::layer::{self, Layer};
+
+/// Renders an error and its chain of sources as a multi-line report.
+///
+/// Each source is shown on its own numbered line. A layer whose message ends with that of its
+/// source, as `Context`'s does, only contributes the part before the repetition (see
+/// [`Report::dedup`]):
+///
+/// ```
+/// use err_ctx::{ErrorExt, Report};
+/// let err = "disk on fire".ctx("writing foo.txt").ctx("saving");
+/// assert_eq!(Report::new(err).to_string(), "\
+/// Error: saving
+///
+/// Caused by:
+///     0: writing foo.txt
+///     1: disk on fire");
+/// ```
+///
+/// The same format is produced by `Context`'s `Display` implementation when the alternate flag is
+/// set, i.e. `format!("{:#}", err)`.
+///
+/// `Report`'s `Debug` implementation renders the same report without the leading "Error: ", which
+/// is supplied by the standard library when an `Err` is returned from `main`. Any error can be
+/// converted into a `Report` with `?`, so the chain is printed legibly on failure, and the process
+/// exits with status 1:
+///
+/// ```no_run
+/// use err_ctx::{Report, ResultExt};
+/// fn main() -> Result<(), Report> {
+///     std::fs::read("foo.txt").ctx("reading foo.txt")?;
+///     Ok(())
+/// }
+/// ```
+pub struct Report {
+    pub(crate) error: Box<dyn Error + Send + Sync>,
+    options: Options,
+    width: Option<Option<usize>>,
+    #[cfg(feature = "std")]
+    exit_code: u8,
+    #[cfg(feature = "color")]
+    color: Option<bool>,
+}
+
+impl Report {
+    pub fn new(error: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self {
+            error: error.into(),
+            options: Options::default(),
+            width: None,
+            #[cfg(feature = "std")]
+            exit_code: 1,
+            #[cfg(feature = "color")]
+            color: None,
+        }
+    }
+
+    /// The status to exit with when this `Report` is returned from `main`. Defaults to 1.
+    ///
+    /// When `main` returns `Result<(), Report>`, the standard library always exits with status 1,
+    /// ignoring any status configured here. To use the configured status, return the `Report`'s
+    /// [`Termination`](std::process::Termination) result instead:
+    ///
+    /// ```no_run
+    /// use std::process::{ExitCode, Termination};
+    /// use err_ctx::{Report, ResultExt};
+    /// fn main() -> ExitCode {
+    ///     match std::fs::read("foo.txt").ctx("reading foo.txt") {
+    ///         Ok(_) => ExitCode::SUCCESS,
+    ///         Err(e) => Report::new(e).exit_code(2).report(),
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn exit_code(mut self, code: u8) -> Self {
+        self.exit_code = code;
+        self
+    }
+
+    /// Whether to append the backtrace captured when the error was first wrapped in a `Context`,
+    /// if any. Defaults to `false`.
+    ///
+    /// Backtraces are only captured when enabled by the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`
+    /// environment variables. Frames belonging to err-ctx itself are omitted.
+    #[cfg(feature = "std")]
+    pub fn show_backtrace(mut self, show: bool) -> Self {
+        self.options.backtrace = show;
+        self
+    }
+
+    /// Whether to show the values attached to each `Context` layer. Defaults to `true`.
+    pub fn show_attachments(mut self, show: bool) -> Self {
+        self.options.attachments = show;
+        self
+    }
+
+    /// Whether to show the source location at which each `Context` layer was constructed.
+    /// Defaults to `false`.
+    pub fn show_locations(mut self, show: bool) -> Self {
+        self.options.locations = show;
+        self
+    }
+
+    /// Whether to style the report with ANSI colors: contexts dimmed, the root cause in bold red,
+    /// and locations in cyan.
+    ///
+    /// By default, colors are used by `Debug`, which renders a `Report` returned from `main`, and by
+    /// [`Termination`](std::process::Termination), if standard error is a terminal. Setting
+    /// `NO_COLOR` disables them, and setting `CLICOLOR_FORCE` enables them even when it is not.
+    /// `Display` only uses colors when they are enabled here, so `to_string` is plain by default.
+    #[cfg(feature = "color")]
+    pub fn color(mut self, enabled: bool) -> Self {
+        self.color = Some(enabled);
+        self
+    }
+
+    /// Whether to omit a repetition of each layer's source at the end of its message, following any
+    /// separator. Layers consisting only of such a repetition are omitted entirely. Defaults to
+    /// `true`.
+    ///
+    /// Many errors include their source's message in their own, so it would otherwise be shown
+    /// twice. Regardless of this setting, `Context` layers only show their own context.
+    ///
+    /// ```
+    /// # use std::{error::Error, fmt};
+    /// use err_ctx::{ErrorExt, Report};
+    /// #[derive(Debug)]
+    /// struct Io(std::io::Error);
+    /// impl fmt::Display for Io {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         write!(f, "io error - {}", self.0)
+    ///     }
+    /// }
+    /// impl Error for Io {
+    ///     fn source(&self) -> Option<&(dyn Error + 'static)> {
+    ///         Some(&self.0)
+    ///     }
+    /// }
+    /// let refused = std::io::Error::new(std::io::ErrorKind::Other, "connection refused");
+    /// let err = Io(refused).ctx("connecting");
+    /// assert_eq!(Report::new(err).to_string(), "\
+    /// Error: connecting
+    ///
+    /// Caused by:
+    ///     0: io error
+    ///     1: connection refused");
+    /// ```
+    pub fn dedup(mut self, enabled: bool) -> Self {
+        self.options.dedup = enabled;
+        self
+    }
+
+    /// The column at which to wrap each layer, with a hanging indent, or `None` not to wrap. Words
+    /// longer than a line, such as paths, are never broken.
+    ///
+    /// By default, `Debug` and `Termination` wrap to the width of the terminal on standard error if
+    /// the `terminal_size` feature is enabled, or else to the value of the `COLUMNS` environment
+    /// variable, if any. `Display` only wraps when a width is given here.
+    ///
+    /// ```
+    /// use err_ctx::{ErrorExt, Report};
+    /// let err = "disk on fire".ctx("writing /var/lib/important/foo.txt");
+    /// assert_eq!(Report::new(err).width(Some(24)).to_string(), "\
+    /// Error: writing
+    ///        /var/lib/important/foo.txt
+    ///
+    /// Caused by:
+    ///     0: disk on fire");
+    /// ```
+    pub fn width(mut self, columns: Option<usize>) -> Self {
+        self.width = Some(columns);
+        self
+    }
+
+    /// The options to render with, given whether to adapt to a terminal on standard error.
+    #[allow(unused_variables)]
+    fn options(&self, terminal: bool) -> Options {
+        #[allow(unused_mut)]
+        let mut options = self.options;
+        #[cfg(feature = "color")]
+        {
+            options.color = match self.color {
+                Some(x) => x,
+                None => terminal && crate::color::detect(),
+            };
+        }
+        options.width = match self.width {
+            Some(x) => x,
+            #[cfg(feature = "std")]
+            None if terminal => crate::terminal::width(),
+            None => None,
+        };
+        options
+    }
+}
+
+impl<E: Into<Box<dyn Error + Send + Sync>>> From<E> for Report {
+    fn from(error: E) -> Self {
+        Self::new(error)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Error: ")?;
+        let top = layer::inspect(&*self.error);
+        write(f, &*self.error, top, &self.options(false))
+    }
+}
+
+impl fmt::Debug for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let top = layer::inspect(&*self.error);
+        write(f, &*self.error, top, &self.options(true))
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::process::Termination for Report {
+    fn report(self) -> std::process::ExitCode {
+        std::eprintln!("Error: {:?}", self);
+        self.exit_code.into()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Options {
+    #[cfg(feature = "std")]
+    backtrace: bool,
+    locations: bool,
+    attachments: bool,
+    dedup: bool,
+    width: Option<usize>,
+    #[cfg(feature = "color")]
+    color: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            backtrace: false,
+            locations: false,
+            attachments: true,
+            dedup: true,
+            width: None,
+            #[cfg(feature = "color")]
+            color: false,
+        }
+    }
+}
+
+/// Write a report for `error`, excluding the leading "Error: ". `top` is the metadata of `error`
+/// itself, if known.
+pub(crate) fn write(
+    f: &mut fmt::Formatter,
+    error: &dyn Error,
+    top: Option<Layer>,
+    options: &Options,
+) -> fmt::Result {
+    let mut layers = iter::once((error, top))
+        .chain(layer::sources(error).map(|(x, layer)| (x as &dyn Error, layer)))
+        .filter_map(|(error, layer)| Some((error, layer, layer_message(error, layer, options)?)));
+    // The root cause has no source to repeat, so at least one layer is always shown
+    if let Some((error, layer, message)) = layers.next() {
+        write_layer(f, error, layer, &message, "       ", options)?;
+    }
+    let mut layers = layers.enumerate().peekable();
+    if layers.peek().is_some() {
+        f.write_str("\n\nCaused by:")?;
+    }
+    for (i, (error, layer, message)) in layers {
+        let prefix = format!("\n    {}: ", i);
+        f.write_str(&prefix)?;
+        write_layer(
+            f,
+            error,
+            layer,
+            &message,
+            &" ".repeat(prefix.len() - 1),
+            options,
+        )?;
+    }
+    #[cfg(feature = "std")]
+    if options.backtrace {
+        // Only the innermost `Context` captures a backtrace, unless an intervening layer hid it
+        let backtrace = iter::once(top)
+            .chain(layer::sources(error).map(|(_, layer)| layer))
+            .filter_map(|layer| Some(layer?.backtrace()?.to_string()))
+            .last();
+        if let Some(backtrace) = backtrace {
+            write!(f, "\n\nStack backtrace:\n{}", trim_backtrace(&backtrace))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `message` and the metadata of a single layer, with continuation lines prefixed by
+/// `indent`.
+fn write_layer(
+    f: &mut fmt::Formatter,
+    error: &dyn Error,
+    layer: Option<Layer>,
+    message: &str,
+    indent: &str,
+    options: &Options,
+) -> fmt::Result {
+    let style = if error.source().is_none() {
+        Style::Root
+    } else if layer.is_some_and(Layer::is_context) {
+        Style::Context
+    } else {
+        Style::Plain
+    };
+    let width = options.width.map(|x| x.saturating_sub(indent.len()));
+    for (i, line) in message.lines().enumerate() {
+        for (j, line) in wrap(line, width).into_iter().enumerate() {
+            if i != 0 || j != 0 {
+                f.write_char('\n')?;
+                if !line.is_empty() {
+                    f.write_str(indent)?;
+                }
+            }
+            paint(f, options, style, line)?;
+        }
+    }
+    let layer = match layer {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+    if options.locations {
+        if let Some(location) = layer.location() {
+            write!(f, "\n{}at ", indent)?;
+            paint(f, options, Style::Location, &location.to_string())?;
+        }
+    }
+    if options.attachments {
+        for (key, value) in layer.attachments() {
+            for line in wrap(&format!("{}: {}", key, value), width) {
+                write!(f, "\n{}{}", indent, line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Split `text` at spaces into lines of at most `width` columns, where possible.
+fn wrap(text: &str, width: Option<usize>) -> Vec<&str> {
+    let width = match width {
+        Some(x) => x,
+        None => return vec![text],
+    };
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut columns = 0;
+    let mut offset = 0;
+    for word in text.split(' ') {
+        let word_start = offset;
+        offset += word.len() + 1;
+        let word_columns = word.chars().count();
+        if word_start == start {
+            columns = word_columns;
+        } else if columns + 1 + word_columns > width {
+            lines.push(&text[start..word_start - 1]);
+            start = word_start;
+            columns = word_columns;
+        } else {
+            columns += 1 + word_columns;
+        }
+    }
+    lines.push(&text[start..]);
+    lines
+}
+
+#[derive(Copy, Clone)]
+enum Style {
+    Plain,
+    Context,
+    Root,
+    Location,
+}
+
+/// Write `text` in `style`, if colors are enabled.
+#[allow(unused_variables)]
+fn paint(f: &mut fmt::Formatter, options: &Options, style: Style, text: &str) -> fmt::Result {
+    #[cfg(feature = "color")]
+    if options.color {
+        use crate::color::*;
+        let code = match style {
+            Style::Plain => return f.write_str(text),
+            Style::Context => DIM,
+            Style::Root => BOLD_RED,
+            Style::Location => CYAN,
+        };
+        return write!(f, "{}{}{}", code, text, RESET);
+    }
+    f.write_str(text)
+}
+
+/// The message to show for `error` in a report, or `None` if it would only repeat its source's.
+fn layer_message(error: &dyn Error, layer: Option<Layer>, options: &Options) -> Option<String> {
+    if layer.is_some_and(Layer::is_context) {
+        return Some(message(error));
+    }
+    let text = error.to_string();
+    if !options.dedup {
+        return Some(text);
+    }
+    let source = match error.source() {
+        Some(x) => x.to_string(),
+        None => return Some(text),
+    };
+    let rest = match text.strip_suffix(&source[..]) {
+        Some(rest) if !source.is_empty() => rest,
+        _ => return Some(text),
+    };
+    let head = rest.trim_end_matches(|c: char| c.is_whitespace() || ":-,;|=>—".contains(c));
+    if !rest.is_empty() && head.len() == rest.len() {
+        // The source's message is only the tail of a word, e.g. "unreachable" after "reachable"
+        return Some(text);
+    }
+    match head.is_empty() {
+        true => None,
+        false => Some(head.to_owned()),
+    }
+}
+
+/// The message of `error`, less any trailing repetition of its source's message.
+pub(crate) fn message(error: &dyn Error) -> String {
+    let text = error.to_string();
+    let source = match error.source() {
+        Some(x) => x.to_string(),
+        None => return text,
+    };
+    match text.strip_suffix(&source[..]) {
+        Some(rest) if rest.ends_with(": ") => rest[..rest.len() - 2].to_owned(),
+        _ => text,
+    }
+}
+
+/// Strip the innermost frames of a rendered backtrace that belong to err-ctx or to the
+/// machinery it invokes to construct a `Context`.
+#[cfg(feature = "std")]
+pub(crate) fn trim_backtrace(backtrace: &str) -> &str {
+    let mut start = 0;
+    for (offset, line) in line_offsets(backtrace) {
+        let frame = line.trim_start();
+        let symbol = match frame.find(": ") {
+            Some(i) if frame[..i].bytes().all(|b| b.is_ascii_digit()) => &frame[i + 2..],
+            // Source locations and other continuation lines belong to the preceding frame
+            _ => continue,
+        };
+        let internal = symbol.contains("err_ctx::")
+            || symbol.starts_with("std::backtrace")
+            || symbol.starts_with("core::result::Result<")
+            || symbol.starts_with("core::option::Option<");
+        if !internal {
+            start = offset;
+            break;
+        }
+    }
+    backtrace[start..].trim_end()
+}
+
+#[cfg(feature = "std")]
+fn line_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len() + 1;
+        Some((start, line))
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug() {
+        fn fallible() -> Result<(), Report> {
+            Err(crate::ErrorExt::ctx("bar", "foo"))?;
+            Ok(())
+        }
+        let report = fallible().unwrap_err().width(None);
+        #[cfg(feature = "color")]
+        let report = report.color(false);
+        assert_eq!(format!("{:?}", report), "foo\n\nCaused by:\n    0: bar");
+        #[cfg(not(feature = "color"))]
+        assert_eq!(format!("{:?}", Report::from("foo")), "foo");
+    }
+
+    #[test]
+    fn dedup() {
+        #[derive(Debug)]
+        struct Wrapper(&'static str, Box<dyn Error + Send + Sync>);
+        impl fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}{}", self.0, self.1)
+            }
+        }
+        impl Error for Wrapper {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&*self.1)
+            }
+        }
+        let report = |prefix, dedup| {
+            let err = crate::ErrorExt::ctx(Wrapper(prefix, "refused".into()), "connecting");
+            Report::new(err).dedup(dedup).to_string()
+        };
+        let expected = "Error: connecting\n\nCaused by:\n    0: io error\n    1: refused";
+        assert_eq!(report("io error: ", true), expected);
+        assert_eq!(report("io error | ", true), expected);
+        assert_eq!(report("io error -> ", true), expected);
+        assert_eq!(
+            report("io error: ", false),
+            "Error: connecting\n\nCaused by:\n    0: io error: refused\n    1: refused"
+        );
+        // Not a repetition
+        assert_eq!(
+            report("un", true),
+            "Error: connecting\n\nCaused by:\n    0: unrefused\n    1: refused"
+        );
+        // Nothing but a repetition
+        assert_eq!(
+            report("", true),
+            "Error: connecting\n\nCaused by:\n    0: refused"
+        );
+        assert_eq!(
+            Report::new(Wrapper("", "refused".into())).to_string(),
+            "Error: refused"
+        );
+
+        // `Context` layers never repeat their sources, whatever their context types
+        let err = crate::ErrorExt::ctx(crate::ErrorExt::ctx("disk", 1u32), "saving");
+        assert_eq!(
+            Report::new(err).dedup(false).to_string(),
+            "Error: saving\n\nCaused by:\n    0: 1\n    1: disk"
+        );
+    }
+
+    #[test]
+    fn wrap() {
+        assert_eq!(super::wrap("a b c", None), ["a b c"]);
+        assert_eq!(super::wrap("aa bb cc dd", Some(5)), ["aa bb", "cc dd"]);
+        assert_eq!(
+            super::wrap("a /long/path b", Some(3)),
+            ["a", "/long/path", "b"]
+        );
+        assert_eq!(super::wrap("", Some(3)), [""]);
+        assert_eq!(super::wrap("ééé ü", Some(5)), ["ééé ü"]);
+
+        let err = || crate::ErrorExt::ctx("the disk is on fire", "writing foo.txt").attach("n", 1);
+        let report = Report::new(err()).width(Some(16));
+        assert_eq!(
+            report.to_string(),
+            "Error: writing
+       foo.txt
+       n: 1
+
+Caused by:
+    0: the disk
+       is on
+       fire"
+        );
+
+        // Disables wrapping to the terminal
+        let report = Report::new(err()).width(None);
+        #[cfg(feature = "color")]
+        let report = report.color(false);
+        assert_eq!(
+            format!("{:?}", report),
+            "writing foo.txt\n       n: 1\n\nCaused by:\n    0: the disk is on fire"
+        );
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn color() {
+        use crate::ErrorExt;
+        let err = "disk on fire".ctx("writing foo.txt").ctx("saving");
+        let report = Report::new(err).show_locations(true).color(true);
+        let text = report.to_string();
+        assert!(text.starts_with("Error: \x1b[2msaving\x1b[0m\n       at \x1b[36msrc/report.rs:"));
+        assert!(text.contains("    0: \x1b[2mwriting foo.txt\x1b[0m\n"));
+        assert!(text.contains("    1: \x1b[1;31mdisk on fire\x1b[0m"));
+        let plain = Report::new("disk on fire").color(false).width(None);
+        assert_eq!(format!("{:?}", plain), "disk on fire");
+    }
+
+    #[test]
+    fn trim() {
+        let backtrace = "   0: err_ctx::Context<C>::new
+             at ./src/lib.rs:1:1
+   1: <T as err_ctx::ErrorExt>::ctx
+             at ./src/lib.rs:2:2
+   2: core::result::Result<T,E>::map_err
+             at /rustc/library/core/src/result.rs:3:3
+   3: app::main
+             at ./src/main.rs:4:4
+   4: std::rt::lang_start
+";
+        assert_eq!(
+            trim_backtrace(backtrace),
+            "   3: app::main
+             at ./src/main.rs:4:4
+   4: std::rt::lang_start"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! Serialization of error chains, for structured logging.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::error::Error;
+use core::fmt;
+
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, SerializeStruct, Serializer};
+
+use crate::layer::{self, Layer};
+use crate::{report, Chain, Context, RemoteError, Report, Structured};
+
+/// Serializes as an object with the fields:
+///
+/// - `message`: the single-line `Display` output
+/// - `contexts`: the context of each `Context` layer, outermost first
+/// - `causes`: the message of each other layer, outermost first
+/// - `attachments`: the attachments of every layer as strings, the outermost taking precedence
+/// - `location`: the location at which this `Context` was constructed
+/// - `backtrace`: the first backtrace captured in the chain, or null
+/// - `layers`: for each layer, outermost first, an object with the fields `message`, `context`
+///   (whether it is a `Context`), `location`, and `attachments`, from which
+///   [`RemoteError`] reconstructs the chain. A `Context`'s message excludes
+///   that of its source.
+///
+/// [`Structured`] contexts, as constructed by `ctx_serde`, are serialized as structured values, and
+/// other contexts as strings, even if they implement `Serialize`. Serializing those structurally
+/// would require bounding this implementation by `C: Serialize`, leaving `Context`s whose context
+/// doesn't implement it unserializable, and wouldn't reach nested layers, whose context types are
+/// erased. Structured contexts must therefore be opted into with `ctx_serde`:
+///
+/// ```
+/// use err_ctx::ErrorExt;
+/// #[derive(Debug, serde::Serialize)]
+/// struct Request {
+///     id: u32,
+/// }
+/// impl std::fmt::Display for Request {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         write!(f, "handling request {}", self.id)
+///     }
+/// }
+/// let json = serde_json::to_value("disk on fire".ctx(Request { id: 1 })).unwrap();
+/// assert_eq!(json["contexts"], serde_json::json!(["handling request 1"]));
+/// let json = serde_json::to_value("disk on fire".ctx_serde(Request { id: 1 })).unwrap();
+/// assert_eq!(json["contexts"], serde_json::json!([{ "id": 1 }]));
+/// ```
+///
+/// ```
+/// use err_ctx::ErrorExt;
+/// let err = "disk on fire".ctx("writing foo.txt").attach("attempt", 3);
+/// let json = serde_json::to_value(&err).unwrap();
+/// assert_eq!(json["contexts"], serde_json::json!(["writing foo.txt"]));
+/// assert_eq!(json["causes"], serde_json::json!(["disk on fire"]));
+/// assert_eq!(json["attachments"]["attempt"], "3");
+/// ```
+impl<C, S> Serialize for Context<C, S>
+where
+    C: fmt::Debug + fmt::Display + 'static,
+    S: fmt::Debug + fmt::Display + 'static,
+{
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let top = Some(Layer::Context(&self.meta));
+        let context = (self.context() as &dyn Any).downcast_ref::<Structured>();
+        serialize(self, top, context, serializer)
+    }
+}
+
+/// Serializes the reported error as [`Context`] does.
+impl Serialize for Report {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let top = layer::inspect(&*self.error);
+        serialize(&*self.error, top, None, serializer)
+    }
+}
+
+/// Serializes as the original chain did, with every context as a string.
+impl Serialize for RemoteError {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        serialize(self, layer::inspect(self), None, serializer)
+    }
+}
+
+/// Serializes the remaining layers as [`Context`] does.
+impl Serialize for Chain<'_> {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        match self.clone().next() {
+            Some(error) => serialize(error, layer::inspect(error), None, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Serialize `error`, whose metadata is `top` and whose context is `context` if it's known to be
+/// `Structured`.
+fn serialize<T: Serializer>(
+    error: &(dyn Error + 'static),
+    top: Option<Layer>,
+    context: Option<&Structured>,
+    serializer: T,
+) -> Result<T::Ok, T::Error> {
+    let mut state = serializer.serialize_struct("Error", 7)?;
+    state.serialize_field("message", &error.to_string())?;
+    let contexts = Contexts {
+        error,
+        top,
+        context,
+    };
+    state.serialize_field("contexts", &contexts)?;
+    let mut causes = Vec::new();
+    for (error, layer) in layer::layers(error, top) {
+        if !layer.is_some_and(Layer::is_context) {
+            causes.push(report::message(error));
+        }
+    }
+    state.serialize_field("causes", &causes)?;
+    state.serialize_field("attachments", &Attachments { error, top })?;
+    let location = top.and_then(Layer::location).map(|x| x.to_string());
+    state.serialize_field("location", &location)?;
+    #[cfg(feature = "std")]
+    let backtrace = layer::layers(error, top)
+        .filter_map(|(_, layer)| Some(layer?.backtrace()?.to_string()))
+        .last()
+        .map(|x| report::trim_backtrace(&x).to_string());
+    #[cfg(not(feature = "std"))]
+    let backtrace = None::<&str>;
+    state.serialize_field("backtrace", &backtrace)?;
+    state.serialize_field("layers", &Layers { error, top })?;
+    state.end()
+}
+
+/// The contexts of a chain, structured where they're known to be `Structured`.
+struct Contexts<'a> {
+    error: &'a (dyn Error + 'static),
+    top: Option<Layer<'a>>,
+    context: Option<&'a Structured>,
+}
+
+impl Serialize for Contexts<'_> {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        for (i, (error, layer)) in layer::layers(self.error, self.top).enumerate() {
+            if !layer.is_some_and(Layer::is_context) {
+                continue;
+            }
+            let nested = error.downcast_ref::<Context<Structured>>();
+            match (i, self.context, nested) {
+                (0, Some(context), _) => seq.serialize_element(context)?,
+                (_, _, Some(x)) => seq.serialize_element(x.context())?,
+                _ => seq.serialize_element(&report::message(error))?,
+            }
+        }
+        seq.end()
+    }
+}
+
+/// The attachments of every layer of a chain, the outermost taking precedence.
+struct Attachments<'a> {
+    error: &'a (dyn Error + 'static),
+    top: Option<Layer<'a>>,
+}
+
+impl Serialize for Attachments<'_> {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let mut seen = Vec::new();
+        let mut map = serializer.serialize_map(None)?;
+        for (_, layer) in layer::layers(self.error, self.top) {
+            for (key, value) in layer.map(Layer::attachments).unwrap_or_default() {
+                if seen.contains(&key) {
+                    continue;
+                }
+                map.serialize_entry(key, &Text(value))?;
+                seen.push(key);
+            }
+        }
+        map.end()
+    }
+}
+
+/// Every layer of a chain, outermost first.
+struct Layers<'a> {
+    error: &'a (dyn Error + 'static),
+    top: Option<Layer<'a>>,
+}
+
+impl Serialize for Layers<'_> {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let layers = layer::layers(self.error, self.top);
+        serializer.collect_seq(layers.map(|(error, layer)| LayerFields { error, layer }))
+    }
+}
+
+/// A single layer of a chain, without its sources.
+struct LayerFields<'a> {
+    error: &'a dyn Error,
+    layer: Option<Layer<'a>>,
+}
+
+impl Serialize for LayerFields<'_> {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let context = self.layer.is_some_and(Layer::is_context);
+        let message = match context {
+            true => report::message(self.error),
+            false => self.error.to_string(),
+        };
+        let location = self.layer.and_then(Layer::location).map(|x| x.to_string());
+        let attachments = self.layer.map(Layer::attachments).unwrap_or_default();
+        let mut state = serializer.serialize_struct("Layer", 4)?;
+        state.serialize_field("message", &message)?;
+        state.serialize_field("context", &context)?;
+        state.serialize_field("location", &location)?;
+        state.serialize_field("attachments", &Pairs(&attachments))?;
+        state.end()
+    }
+}
+
+/// Key-value pairs, serialized as a map.
+struct Pairs<'a>(&'a [(&'a str, &'a dyn fmt::Display)]);
+
+impl Serialize for Pairs<'_> {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        serializer.collect_map(self.0.iter().map(|&(k, v)| (k, Text(v))))
+    }
+}
+
+/// A value serialized as its `Display` output.
+struct Text<'a>(&'a dyn fmt::Display);
+
+impl Serialize for Text<'_> {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        serializer.collect_str(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::{ErrorExt, Message};
+
+    #[derive(Debug, serde::Serialize)]
+    struct Request {
+        id: u32,
+    }
+
+    impl fmt::Display for Request {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "handling request {}", self.id)
+        }
+    }
+
+    #[test]
+    fn structured() {
+        let err = Message::new("disk on fire")
+            .ctx_serde(Request { id: 1 })
+            .attach("attempt", 3)
+            .ctx_serde(Request { id: 2 })
+            .attach("attempt", 4)
+            .attach("user", "alice");
+        let ids = err.contexts::<Request>().map(|x| x.id);
+        assert_eq!(ids.collect::<Vec<_>>(), [2, 1]);
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(
+            json["message"],
+            "handling request 2: handling request 1: disk on fire"
+        );
+        assert_eq!(json["contexts"], json!([{ "id": 2 }, { "id": 1 }]));
+        assert_eq!(json["causes"], json!(["disk on fire"]));
+        assert_eq!(
+            json["attachments"],
+            json!({ "attempt": "4", "user": "alice" })
+        );
+        assert!(json["location"]
+            .as_str()
+            .unwrap()
+            .starts_with("src/serialize.rs:"));
+        #[cfg(not(feature = "std"))]
+        assert_eq!(json["backtrace"], json!(null));
+    }
+
+    #[test]
+    fn erased() {
+        let err = "disk on fire".ctx_serde(Request { id: 1 }).ctx("saving");
+        let json = serde_json::to_value(Report::new(err)).unwrap();
+        assert_eq!(json["contexts"], json!(["saving", { "id": 1 }]));
+        assert_eq!(json["causes"], json!(["disk on fire"]));
+
+        // Contexts not constructed with `ctx_serde` are serialized as strings, even if `Serialize`
+        let err = "disk on fire"
+            .ctx("handling request 1")
+            .ctx(Request { id: 2 });
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(
+            json["contexts"],
+            json!(["handling request 2", "handling request 1"])
+        );
+        assert_eq!(json["causes"], json!(["disk on fire"]));
+
+        // Nested layers are recognized as `Context`s whatever their types
+        let err = Message::new("disk on fire")
+            .ctx_typed(Request { id: 1 })
+            .attach("attempt", 2)
+            .ctx("saving");
+        let json = serde_json::to_value(Report::new(err)).unwrap();
+        assert_eq!(json["contexts"], json!(["saving", "handling request 1"]));
+        assert_eq!(json["causes"], json!(["disk on fire"]));
+        assert_eq!(json["attachments"], json!({ "attempt": "2" }));
+
+        let json = serde_json::to_value(Report::new("disk on fire")).unwrap();
+        assert_eq!(json["message"], "disk on fire");
+        assert_eq!(json["contexts"], json!([]));
+        assert_eq!(json["causes"], json!(["disk on fire"]));
+        assert_eq!(json["attachments"], json!({}));
+        assert_eq!(json["location"], json!(null));
+    }
+
+    #[test]
+    fn chain() {
+        let err = "disk on fire".ctx("writing foo.txt").ctx("saving");
+        let mut chain = Chain::new(&err);
+        chain.next();
+        let json = serde_json::to_value(&chain).unwrap();
+        assert_eq!(json["message"], "writing foo.txt: disk on fire");
+        assert_eq!(json["contexts"], json!(["writing foo.txt"]));
+        assert_eq!(json["causes"], json!(["disk on fire"]));
+    }
+}
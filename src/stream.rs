@@ -0,0 +1,217 @@
+use alloc::boxed::Box;
+use core::error::Error;
+use core::panic::Location;
+use core::pin::Pin;
+use core::task::{self, Poll};
+
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+
+use crate::{Context, ErrorExt};
+
+/// Extension methods for streams of `Result`s.
+///
+/// ```
+/// # use futures_core::Stream;
+/// use err_ctx::TryStreamCtxExt;
+/// # fn example(records: impl Stream<Item = Result<Vec<u8>, std::io::Error>>) {
+/// let records = records.with_ctx(|i, _| format!("reading record {}", i));
+/// # }
+/// ```
+pub trait TryStreamCtxExt<T, E>: Stream<Item = Result<T, E>> + Sized
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    /// Wrap each `Err` item with a clone of `context`.
+    #[track_caller]
+    fn ctx<D: Clone>(self, context: D) -> CtxStream<Self, D>;
+
+    /// Wrap each `Err` item with the result of invoking `f` on the item's zero-based index in the
+    /// stream and the error.
+    #[track_caller]
+    fn with_ctx<D, F>(self, f: F) -> WithCtxStream<Self, F>
+    where
+        F: FnMut(usize, &E) -> D;
+}
+
+impl<T, E, St> TryStreamCtxExt<T, E> for St
+where
+    St: Stream<Item = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    #[track_caller]
+    fn ctx<D: Clone>(self, context: D) -> CtxStream<Self, D> {
+        CtxStream {
+            stream: self,
+            context,
+            location: Location::caller(),
+        }
+    }
+
+    #[track_caller]
+    fn with_ctx<D, F>(self, f: F) -> WithCtxStream<Self, F>
+    where
+        F: FnMut(usize, &E) -> D,
+    {
+        WithCtxStream {
+            stream: self,
+            f,
+            index: 0,
+            location: Location::caller(),
+        }
+    }
+}
+
+pin_project! {
+    /// Stream returned by [`TryStreamCtxExt::ctx`].
+    #[must_use = "streams do nothing unless polled"]
+    #[derive(Debug)]
+    pub struct CtxStream<St, D> {
+        #[pin]
+        stream: St,
+        context: D,
+        location: &'static Location<'static>,
+    }
+}
+
+impl<T, E, St, D> Stream for CtxStream<St, D>
+where
+    St: Stream<Item = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+    D: Clone,
+{
+    type Item = Result<T, Context<D>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let (context, location) = (this.context, *this.location);
+        this.stream
+            .poll_next(cx)
+            .map(|item| Some(item?.map_err(|e| e.ctx(context.clone()).at(location))))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+pin_project! {
+    /// Stream returned by [`TryStreamCtxExt::with_ctx`].
+    #[must_use = "streams do nothing unless polled"]
+    #[derive(Debug)]
+    pub struct WithCtxStream<St, F> {
+        #[pin]
+        stream: St,
+        f: F,
+        index: usize,
+        location: &'static Location<'static>,
+    }
+}
+
+impl<T, E, St, D, F> Stream for WithCtxStream<St, F>
+where
+    St: Stream<Item = Result<T, E>>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+    F: FnMut(usize, &E) -> D,
+{
+    type Item = Result<T, Context<D>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let (f, index, location) = (this.f, this.index, *this.location);
+        this.stream.poll_next(cx).map(|item| {
+            let i = *index;
+            *index += 1;
+            Some(item?.map_err(|e| {
+                let context = f(i, &e);
+                e.ctx(context).at(location)
+            }))
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use alloc::{format, vec};
+
+    use crate::future::tests::noop_waker;
+
+    /// Yields the contents of a `Vec`, returning `Pending` before each item.
+    struct Items<T> {
+        items: alloc::vec::IntoIter<T>,
+        ready: bool,
+    }
+
+    impl<T: Unpin> Stream for Items<T> {
+        type Item = T;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Option<T>> {
+            self.ready = !self.ready;
+            if self.ready {
+                Poll::Ready(self.items.next())
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    fn items<T>(items: Vec<T>) -> Items<T> {
+        Items {
+            items: items.into_iter(),
+            ready: false,
+        }
+    }
+
+    fn collect<St: Stream>(stream: St) -> Vec<St::Item> {
+        let waker = noop_waker();
+        let mut cx = task::Context::from_waker(&waker);
+        let mut stream = Box::pin(stream);
+        let mut out = Vec::new();
+        loop {
+            match stream.as_mut().poll_next(&mut cx) {
+                Poll::Ready(Some(x)) => out.push(x),
+                Poll::Ready(None) => return out,
+                Poll::Pending => {}
+            }
+        }
+    }
+
+    fn messages<T, D: core::fmt::Display>(items: Vec<Result<T, Context<D>>>) -> Vec<String> {
+        items
+            .into_iter()
+            .map(|x| x.map_or_else(|e| e.to_string(), |_| "ok".into()))
+            .collect()
+    }
+
+    #[test]
+    fn ctx() {
+        let stream = items(vec![Ok(1), Err("bar"), Ok(2), Err("baz")]).ctx("foo");
+        assert_eq!(
+            messages(collect(stream)),
+            ["ok", "foo: bar", "ok", "foo: baz"]
+        );
+    }
+
+    #[test]
+    fn with_ctx_index() {
+        let stream = items(vec![Ok(1), Err("bar"), Ok(2), Err("baz")])
+            .with_ctx(|i, e| format!("reading record {} ({} bytes)", i, e.len()));
+        assert_eq!(
+            messages(collect(stream)),
+            [
+                "ok",
+                "reading record 1 (3 bytes): bar",
+                "ok",
+                "reading record 3 (3 bytes): baz"
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use alloc::boxed::Box;
+use core::any::Any;
+use core::fmt;
+
+use serde::ser::{Serialize, Serializer};
+
+/// A context of any serializable type, as constructed by [`ResultExt::ctx_serde`] and
+/// [`ErrorExt::ctx_serde`], which is serialized as a structured value wherever it appears in an
+/// error's chain.
+///
+/// Other contexts are serialized as strings, even if they implement `Serialize`, since the type of
+/// a nested context is erased along with its serializer.
+///
+/// ```
+/// use err_ctx::ErrorExt;
+/// #[derive(Debug, serde::Serialize)]
+/// struct Request {
+///     id: u32,
+/// }
+/// impl std::fmt::Display for Request {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         write!(f, "handling request {}", self.id)
+///     }
+/// }
+/// let err = "disk on fire".ctx_serde(Request { id: 1 }).ctx("saving");
+/// let json = serde_json::to_value(&err).unwrap();
+/// assert_eq!(json["contexts"], serde_json::json!(["saving", { "id": 1 }]));
+/// assert_eq!(err.find_context::<Request>().unwrap().id, 1);
+/// ```
+///
+/// [`ResultExt::ctx_serde`]: crate::ResultExt::ctx_serde
+/// [`ErrorExt::ctx_serde`]: crate::ErrorExt::ctx_serde
+pub struct Structured(Box<dyn Value>);
+
+impl Structured {
+    pub fn new<T>(value: T) -> Self
+    where
+        T: Serialize + fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        Self(Box::new(value))
+    }
+
+    /// The value, if it has type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref()
+    }
+}
+
+impl fmt::Debug for Structured {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.as_debug().fmt(f)
+    }
+}
+
+impl fmt::Display for Structured {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.as_display().fmt(f)
+    }
+}
+
+impl Serialize for Structured {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        erased_serde::serialize(self.0.as_serialize(), serializer)
+    }
+}
+
+/// A type-erased structured context.
+trait Value: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_debug(&self) -> &dyn fmt::Debug;
+    fn as_display(&self) -> &dyn fmt::Display;
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize;
+}
+
+impl<T> Value for T
+where
+    T: Serialize + fmt::Display + fmt::Debug + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_debug(&self) -> &dyn fmt::Debug {
+        self
+    }
+
+    fn as_display(&self) -> &dyn fmt::Display {
+        self
+    }
+
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize {
+        self
+    }
+}
@@ -0,0 +1,14 @@
+//! Detection of the terminal reports are printed to.
+
+use std::env;
+
+/// The width of the terminal on standard error in columns, or failing that the value of `COLUMNS`.
+pub(crate) fn width() -> Option<usize> {
+    #[cfg(feature = "terminal_size")]
+    if let Some((terminal_size::Width(width), _)) =
+        terminal_size::terminal_size_of(std::io::stderr())
+    {
+        return Some(width.into());
+    }
+    env::var("COLUMNS").ok()?.parse().ok()
+}